@@ -0,0 +1,331 @@
+use crate::error::GuitarToolsError;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+// Playback goes through rodio, which brings its own (newer) cpal. Its traits are only needed for their methods.
+use rodio::{
+    cpal::traits::{DeviceTrait as _, HostTrait as _},
+    OutputStream, OutputStreamHandle,
+};
+use serde::{Deserialize, Serialize};
+
+// Sample rates offered for devices that support a continuous range, alongside the range's own ends
+const COMMON_SAMPLE_RATES: [u32; 6] = [22050, 44100, 48000, 88200, 96000, 192000];
+
+// Audio Recording --------------------------------------------------------------------------------------------------------------
+// This portion is based on the "cpal:recording" section of https://www.youtube.com/watch?v=ZweInbMBsa4
+pub struct AudioData {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+// Input Selection --------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Which audio host, device, format and channel to record from
+   Notes: None means the system default for the host and device. For the config it means the device's first config at its highest rate.
+          Devices are remembered by name, since that's all cpal offers that stays the same between runs.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputSettings {
+    pub host: Option<String>,
+    pub device: Option<String>,
+    pub config: Option<InputConfig>,
+    pub channel: InputChannel,
+}
+
+/*
+   Purpose: A channel count and sample rate the input device supports
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct InputConfig {
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+impl InputConfig {
+    pub fn label(&self) -> String {
+        let channels = match self.channels {
+            1 => "Mono".to_string(),
+            2 => "Stereo".to_string(),
+            count => format!("{} channels", count),
+        };
+        format!("{} at {} Hz", channels, self.sample_rate)
+    }
+}
+
+/*
+   Purpose: Which channel of a multi-channel input is analysed
+   Notes: Channels count from 0 here and are shown to the user counting from 1, like the inputs printed on an interface.
+          Mixdown averages every channel into one, for when the guitar could be on any of them.
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputChannel {
+    Channel(u16),
+    Mixdown,
+}
+
+impl Default for InputChannel {
+    fn default() -> Self {
+        InputChannel::Channel(0)
+    }
+}
+
+impl InputChannel {
+    pub fn label(&self) -> String {
+        match self {
+            InputChannel::Channel(index) => format!("Input {}", index + 1),
+            InputChannel::Mixdown => "Mix down to mono".to_string(),
+        }
+    }
+}
+
+/*
+   Purpose: The names of the audio hosts available on this system, e.g. ALSA and JACK on Linux or WASAPI and ASIO on Windows
+*/
+pub fn input_hosts() -> Vec<String> {
+    cpal::available_hosts()
+        .into_iter()
+        .map(|host| host.name().to_string())
+        .collect()
+}
+
+/*
+   Purpose: The names of the input devices on a host. None means the default host.
+*/
+pub fn input_devices(host: Option<&str>) -> Result<Vec<String>, GuitarToolsError> {
+    let host = find_host(host)?;
+    let devices = host
+        .input_devices()
+        .map_err(GuitarToolsError::device)?
+        .filter_map(|device| device.name().ok())
+        .collect();
+    Ok(devices)
+}
+
+/*
+   Purpose: The channel counts and sample rates an input device can record at, sorted and without repeats
+   Notes: Devices often report a range of rates rather than a list, so the range is offered at its ends and at any COMMON_SAMPLE_RATES inside it.
+*/
+pub fn input_configs(
+    host: Option<&str>,
+    device: Option<&str>,
+) -> Result<Vec<InputConfig>, GuitarToolsError> {
+    let device = find_device(&find_host(host)?, device)?;
+    let mut configs = Vec::new();
+    for range in device
+        .supported_input_configs()
+        .map_err(GuitarToolsError::config)?
+    {
+        let (low, high) = (range.min_sample_rate().0, range.max_sample_rate().0);
+        let rates = COMMON_SAMPLE_RATES
+            .into_iter()
+            .filter(|rate| (low..=high).contains(rate))
+            .chain([low, high]);
+        for sample_rate in rates {
+            configs.push(InputConfig {
+                channels: range.channels(),
+                sample_rate,
+            });
+        }
+    }
+    configs.sort();
+    configs.dedup();
+    Ok(configs)
+}
+
+fn find_host(name: Option<&str>) -> Result<cpal::Host, GuitarToolsError> {
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(cpal::default_host()),
+    };
+    let unavailable = || GuitarToolsError::HostUnavailable(name.to_string());
+    let id = cpal::available_hosts()
+        .into_iter()
+        .find(|host| host.name() == name)
+        .ok_or_else(unavailable)?;
+    cpal::host_from_id(id).map_err(|_| unavailable())
+}
+
+fn find_device(host: &cpal::Host, name: Option<&str>) -> Result<cpal::Device, GuitarToolsError> {
+    match name {
+        Some(name) => host
+            .input_devices()
+            .map_err(GuitarToolsError::device)?
+            .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+            .ok_or_else(|| GuitarToolsError::DeviceNotFound(name.to_string())),
+        None => host
+            .default_input_device()
+            .ok_or(GuitarToolsError::NoInputDevice),
+    }
+}
+
+// Input Stream -----------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Opens the chosen input device and starts streaming the chosen channel into a ring buffer
+   Notes: This is based on https://docs.rs/cpal/0.15.2/cpal/, https://github.com/RustAudio/cpal/blob/master/examples/record_wav.rs#L129 and https://www.youtube.com/watch?v=ZweInbMBsa4
+          Returns the stream (which has to be kept alive), the sample rate and the reading end of the ring buffer.
+          on_error is called from the audio thread if the stream breaks after it has started, e.g. when the device is unplugged.
+*/
+pub fn open_input_stream(
+    input: &InputSettings,
+    mut on_error: impl FnMut(GuitarToolsError) + Send + 'static,
+) -> Result<(cpal::Stream, u32, HeapConsumer<f32>), GuitarToolsError> {
+    let host = find_host(input.host.as_deref())?;
+    let device = find_device(&host, input.device.as_deref())?;
+
+    let mut supported_configs_range = device
+        .supported_input_configs()
+        .map_err(GuitarToolsError::config)?;
+
+    let supported_config = match input.config {
+        Some(config) => supported_configs_range
+            .find(|range| {
+                range.channels() == config.channels
+                    && (range.min_sample_rate().0..=range.max_sample_rate().0)
+                        .contains(&config.sample_rate)
+            })
+            .ok_or_else(|| {
+                GuitarToolsError::config(format!("the device doesn't support {}", config.label()))
+            })?
+            .with_sample_rate(cpal::SampleRate(config.sample_rate)),
+        None => supported_configs_range
+            .next()
+            .ok_or_else(|| GuitarToolsError::config("the device has no supported formats"))?
+            .with_max_sample_rate(),
+    };
+
+    let sample_rate = supported_config.sample_rate().0;
+    let channels = supported_config.channels();
+    let channel = input.channel;
+    if let InputChannel::Channel(index) = channel {
+        if index >= channels {
+            return Err(GuitarToolsError::config(format!(
+                "{} was chosen but the input only has {} channels",
+                channel.label(),
+                channels
+            )));
+        }
+    }
+
+    // Two seconds of room so a slow analysis pass doesn't cause dropped samples
+    let (mut producer, consumer) = HeapRb::<f32>::new(sample_rate as usize * 2).split();
+
+    /*
+       Notes: These were taken from https://github.com/RustAudio/cpal/blob/master/examples/record_wav.rs#L129 and https://www.youtube.com/watch?v=ZweInbMBsa4
+              If the ring buffer is full the newest samples are dropped; the analysis thread will catch up on the next pass.
+    */
+    fn write_input_data<T>(
+        input: &[T],
+        channels: u16,
+        channel: InputChannel,
+        producer: &mut HeapProducer<f32>,
+    ) where
+        T: cpal::Sample,
+    {
+        for frame in input.chunks(channels.into()) {
+            let sample = match channel {
+                InputChannel::Channel(index) => frame[index as usize].to_f32(),
+                InputChannel::Mixdown => {
+                    frame.iter().map(|sample| sample.to_f32()).sum::<f32>() / frame.len() as f32
+                }
+            };
+            if producer.push(sample).is_err() {
+                break;
+            }
+        }
+    }
+
+    let name = device.name().unwrap_or_default();
+    let err_fn = move |err| {
+        on_error(match err {
+            cpal::StreamError::DeviceNotAvailable => GuitarToolsError::DeviceNotFound(name.clone()),
+            err => GuitarToolsError::stream(err),
+        })
+    };
+
+    //Does the writing based on the device data type
+    let stream = match supported_config.sample_format() {
+        cpal::SampleFormat::F32 => device.build_input_stream(
+            &supported_config.into(),
+            move |data, _: &_| write_input_data::<f32>(data, channels, channel, &mut producer),
+            err_fn,
+        ),
+        cpal::SampleFormat::I16 => device.build_input_stream(
+            &supported_config.into(),
+            move |data, _: &_| write_input_data::<i16>(data, channels, channel, &mut producer),
+            err_fn,
+        ),
+        cpal::SampleFormat::U16 => device.build_input_stream(
+            &supported_config.into(),
+            move |data, _: &_| write_input_data::<u16>(data, channels, channel, &mut producer),
+            err_fn,
+        ),
+    }
+    .map_err(GuitarToolsError::stream)?;
+
+    stream.play().map_err(GuitarToolsError::stream)?;
+
+    Ok((stream, sample_rate, consumer))
+}
+
+// Audio Output -----------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: The names of the output devices on the default host, e.g. headphones or an audio interface
+*/
+pub fn output_devices() -> Result<Vec<String>, GuitarToolsError> {
+    let devices = rodio::cpal::default_host()
+        .output_devices()
+        .map_err(GuitarToolsError::device)?
+        .filter_map(|device| device.name().ok())
+        .collect();
+    Ok(devices)
+}
+
+/*
+   Purpose: Finds the output device with the given name, or the system default for None
+*/
+fn find_output_device(device: Option<&str>) -> Result<rodio::Device, GuitarToolsError> {
+    let host = rodio::cpal::default_host();
+    match device {
+        Some(name) => host
+            .output_devices()
+            .map_err(GuitarToolsError::device)?
+            .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+            .ok_or_else(|| GuitarToolsError::DeviceNotFound(name.to_string())),
+        None => host
+            .default_output_device()
+            .ok_or(GuitarToolsError::NoOutputDevice),
+    }
+}
+
+/*
+   Purpose: The name of the system's default output device, or None if there isn't one
+   Notes: Unlike listing the devices, this doesn't open any of them, so it's cheap enough to check before every tone.
+*/
+pub fn default_output_device_name() -> Option<String> {
+    rodio::cpal::default_host()
+        .default_output_device()
+        .and_then(|device| device.name().ok())
+}
+
+/*
+   Purpose: Opens an output device for playback at its native sample rate. None means the system default.
+   Notes: Based on https://docs.rs/rodio/latest/rodio/struct.OutputStream.html
+          Returns the stream (which has to be kept alive while anything plays), a handle to play through, the device's sample rate
+          and its name. Rendering sounds at that rate means nothing has to be resampled on the way out.
+*/
+pub fn open_output_stream(
+    device: Option<&str>,
+) -> Result<(OutputStream, OutputStreamHandle, u32, String), GuitarToolsError> {
+    let device = find_output_device(device)?;
+    let name = device.name().map_err(GuitarToolsError::device)?;
+
+    let sample_rate = device
+        .default_output_config()
+        .map_err(GuitarToolsError::config)?
+        .sample_rate()
+        .0;
+    let (stream, handle) =
+        OutputStream::try_from_device(&device).map_err(GuitarToolsError::stream)?;
+    Ok((stream, handle, sample_rate, name))
+}
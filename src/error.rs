@@ -0,0 +1,70 @@
+use std::{error::Error, fmt};
+
+// ERRORS -----------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Why recording, playing or analysing audio failed
+   Notes: These are shown to the user, who can usually fix them by plugging something in or picking another device or format and retrying.
+          The audio libraries' own errors are kept as text, since there are two versions of cpal in use (one through rodio).
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GuitarToolsError {
+    NoInputDevice,
+    NoOutputDevice,
+    // A device that was picked by name isn't connected any more
+    DeviceNotFound(String),
+    HostUnavailable(String),
+    // The audio system failed while listing or asking about devices
+    Device(String),
+    // The device can't record or play in the chosen format
+    Config(String),
+    // A stream couldn't be opened or started, or stopped working part way through
+    Stream(String),
+    Analysis(String),
+}
+
+impl GuitarToolsError {
+    pub(crate) fn device(err: impl fmt::Display) -> Self {
+        GuitarToolsError::Device(err.to_string())
+    }
+
+    pub(crate) fn config(err: impl fmt::Display) -> Self {
+        GuitarToolsError::Config(err.to_string())
+    }
+
+    pub(crate) fn stream(err: impl fmt::Display) -> Self {
+        GuitarToolsError::Stream(err.to_string())
+    }
+
+    pub(crate) fn analysis(err: impl fmt::Display) -> Self {
+        GuitarToolsError::Analysis(err.to_string())
+    }
+}
+
+impl fmt::Display for GuitarToolsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuitarToolsError::NoInputDevice => write!(
+                f,
+                "no input device was found, plug in a microphone or audio interface"
+            ),
+            GuitarToolsError::NoOutputDevice => write!(
+                f,
+                "no output device was found, plug in speakers or headphones"
+            ),
+            GuitarToolsError::DeviceNotFound(name) => {
+                write!(f, "the device \"{}\" is not connected", name)
+            }
+            GuitarToolsError::HostUnavailable(name) => {
+                write!(f, "the audio host \"{}\" is not available", name)
+            }
+            GuitarToolsError::Device(err) => write!(f, "the audio device failed: {}", err),
+            GuitarToolsError::Config(err) => write!(f, "unsupported audio format: {}", err),
+            GuitarToolsError::Stream(err) => write!(f, "the audio stream failed: {}", err),
+            GuitarToolsError::Analysis(err) => {
+                write!(f, "the recording couldn't be analysed: {}", err)
+            }
+        }
+    }
+}
+
+impl Error for GuitarToolsError {}
@@ -0,0 +1,13 @@
+// The reusable parts of Guitar Tools: note frequencies, pitch detection, audio input/output and the live tuner.
+// The eframe app in main.rs is a thin layer over these.
+pub mod audio_io;
+pub mod error;
+pub mod notes;
+pub mod pitch;
+pub mod relative;
+pub mod settings;
+pub mod spectrogram;
+pub mod synth;
+pub mod tuner;
+pub mod tuning;
+pub mod visualise;
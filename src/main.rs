@@ -1,17 +1,30 @@
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use eframe::egui;
-use realfft::RealFftPlanner;
-use rodio::{buffer::SamplesBuffer, OutputStream, Sink};
-use std::{
-    cmp::Ordering,
-    error::Error,
-    f32::consts::PI,
-    sync::{Arc, Mutex},
+use guitar_tools::{
+    audio_io::{
+        input_configs, input_devices, input_hosts, output_devices, InputChannel, InputConfig,
+    },
+    error::GuitarToolsError,
+    notes::{Pitch, STANDARD_A4},
+    pitch::DetectorKind,
+    relative::{relative_steps, RelativeMethod},
+    settings::{Settings, REFERENCE_A4_RANGE},
+    synth::{
+        SequenceOrder, SequenceStyle, Timbre, TonePlayer, BRIGHTNESS_RANGE, DECAY_RANGE, GAP_RANGE,
+        VOLUME_RANGE_DB,
+    },
+    tuner::{advice, LiveTuner, TunerReading},
+    tuning::{
+        back_up_custom_tunings, built_in_tunings, load_custom_tunings, lowest_string,
+        save_custom_tunings, validate_tunings, Instrument, Tuning, STRING_COUNT_RANGE,
+    },
 };
+use std::{path::PathBuf, time::Duration};
+
+mod widgets;
 
 const SPACING: f32 = 10.0;
-const VOLUME_SCALER: f32 = 0.1;
-const HARMONIC_GUARD: usize = 20;
+// How often the "Tune by ear" page checks whether the tone has finished, so the stop button greys out on time
+const PLAYBACK_REPAINT_INTERVAL: Duration = Duration::from_millis(100);
 
 // MAIN -------------------------------------------------------------------------------------------------------------------------
 fn main() -> Result<(), eframe::Error> {
@@ -28,28 +41,128 @@ fn main() -> Result<(), eframe::Error> {
 // Adapted from eframe documentation: https://docs.rs/eframe/latest/eframe/
 struct GuitarToolsApp {
     app_mode: AppModeOptions,
-    volume: i32,
-    tuning_result: String,
+    tone_player: TonePlayer,
+    live_tuner: LiveTuner,
+    settings: Settings,
+    // The built-in tunings followed by the user's own
+    tunings: Vec<Tuning>,
+    tuning_editor: TuningEditor,
+    spectrogram_view: widgets::SpectrogramView,
+    input_choices: InputChoices,
+    output_choices: OutputChoices,
+    relative_guide: RelativeGuide,
+    // What last failed to play and why, so it can be retried
+    playback_error: Option<(Playback, GuitarToolsError)>,
 }
 
 impl Default for GuitarToolsApp {
     fn default() -> Self {
+        let (custom_tunings, message) = match load_custom_tunings() {
+            Ok(loaded) if loaded.skipped.is_empty() => (loaded.tunings, None),
+            Ok(loaded) => (
+                loaded.tunings,
+                Some(format!(
+                    "Some of your custom tunings could not be loaded, so they aren't shown. Saving will first move the file to tunings.toml.bak. ({})",
+                    loaded.skipped.join("; ")
+                )),
+            ),
+            Err(err) => (
+                vec![],
+                Some(format!(
+                    "Your custom tunings could not be loaded, so none are shown. Saving will first move the file to tunings.toml.bak. ({})",
+                    err
+                )),
+            ),
+        };
+        let back_up = message.is_some();
+
+        let settings = Settings::load();
         Self {
             app_mode: AppModeOptions::Home,
-            volume: 10,
-            tuning_result: "Result: N/A".to_string(),
+            tone_player: TonePlayer::new(settings.volume_db),
+            live_tuner: LiveTuner::default(),
+            settings,
+            tunings: all_tunings(&custom_tunings),
+            tuning_editor: TuningEditor {
+                drafts: custom_tunings.iter().map(TuningDraft::from).collect(),
+                message,
+                back_up,
+            },
+            spectrogram_view: widgets::SpectrogramView::default(),
+            input_choices: InputChoices::default(),
+            output_choices: OutputChoices::default(),
+            relative_guide: RelativeGuide::default(),
+            playback_error: None,
         }
     }
 }
 
+fn all_tunings(custom_tunings: &[Tuning]) -> Vec<Tuning> {
+    let mut tunings = built_in_tunings();
+    tunings.extend_from_slice(custom_tunings);
+    tunings
+}
+
 impl eframe::App for GuitarToolsApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        draw_menu(&mut self.app_mode, ctx);
+        let previous_mode = self.app_mode;
+        draw_menu(&mut self.app_mode, &mut self.settings, ctx);
+        self.live_tuner.set_reference_a4(self.settings.reference_a4);
+        self.live_tuner.set_detector(self.settings.detector);
+        if let Some(pitch) = lowest_string(&self.tunings, self.settings.instrument) {
+            self.live_tuner.set_lowest_string(pitch);
+        }
+        let instrument = self.settings.instrument;
+        let tunings: Vec<&Tuning> = self
+            .tunings
+            .iter()
+            .filter(|tuning| tuning.instrument == instrument)
+            .collect();
+
+        // There's no reason to keep the microphone open once the user has moved on to another page
+        if !matches!(
+            self.app_mode,
+            AppModeOptions::TuneByRecording | AppModeOptions::TuneRelative
+        ) {
+            self.live_tuner.stop();
+        }
+        // Likewise a drone would otherwise keep going on a page with no way to stop it. Only once, as the page is left.
+        if previous_mode == AppModeOptions::TuneByEar && self.app_mode != AppModeOptions::TuneByEar
+        {
+            self.tone_player.stop();
+        }
 
         match self.app_mode {
             AppModeOptions::Home => draw_home(ctx),
-            AppModeOptions::TuneByEar => draw_tune_by_ear(ctx, &mut self.volume),
-            AppModeOptions::TuneByRecording => draw_tune_by_recording(ctx, &mut self.tuning_result),
+            AppModeOptions::TuneByEar => draw_tune_by_ear(
+                ctx,
+                &tunings,
+                &mut self.tone_player,
+                &mut self.settings,
+                &mut self.output_choices,
+                &mut self.playback_error,
+            ),
+            AppModeOptions::TuneByRecording => draw_tune_by_recording(
+                ctx,
+                &tunings,
+                &mut self.live_tuner,
+                &mut self.settings,
+                &mut self.spectrogram_view,
+                &mut self.input_choices,
+            ),
+            AppModeOptions::TuneRelative => draw_tune_relative(
+                ctx,
+                &tunings,
+                &mut self.live_tuner,
+                &mut self.settings,
+                &mut self.input_choices,
+                &mut self.relative_guide,
+            ),
+            AppModeOptions::EditTunings => {
+                if let Some(custom_tunings) = draw_tuning_editor(ctx, &mut self.tuning_editor) {
+                    self.tunings = all_tunings(&custom_tunings);
+                }
+            }
         }
     }
 }
@@ -58,7 +171,7 @@ impl eframe::App for GuitarToolsApp {
    Purpose: This draws the top-most "menu" that allows users to select which portion of the app they would like to use
    Notes: Default is the "Home" portion
 */
-fn draw_menu(app_mode: &mut AppModeOptions, ctx: &egui::Context) {
+fn draw_menu(app_mode: &mut AppModeOptions, settings: &mut Settings, ctx: &egui::Context) {
     egui::TopBottomPanel::top("Heading Panel").show(ctx, |ui| {
         ui.heading("Navigation:");
         ui.end_row();
@@ -70,10 +183,71 @@ fn draw_menu(app_mode: &mut AppModeOptions, ctx: &egui::Context) {
                 AppModeOptions::TuneByRecording,
                 "Tune by recording",
             );
+            ui.radio_value(
+                app_mode,
+                AppModeOptions::TuneRelative,
+                "Tune string to string",
+            );
+            ui.radio_value(app_mode, AppModeOptions::EditTunings, "Edit tunings");
         });
+        draw_reference_pitch(ui, settings);
+        draw_instrument(ui, settings);
     });
 }
 
+/*
+   Purpose: Draws the concert pitch (A4) setting, which both tuning modes use
+   Notes: The settings file is only written once the user lets go of the slider, rather than on every frame of a drag.
+*/
+fn draw_reference_pitch(ui: &mut egui::Ui, settings: &mut Settings) {
+    ui.horizontal(|ui| {
+        ui.label("Concert pitch: A4 =");
+        let response = ui.add(
+            egui::Slider::new(&mut settings.reference_a4, REFERENCE_A4_RANGE)
+                .suffix(" Hz")
+                .fixed_decimals(1),
+        );
+        if ui.button("Reset to 440").clicked() {
+            settings.reference_a4 = STANDARD_A4;
+            save_settings(settings);
+        }
+        if response.drag_released() || (response.changed() && !response.dragged()) {
+            save_settings(settings);
+        }
+    });
+}
+
+/*
+   Purpose: Draws the instrument picker, which decides which tunings both tuning pages list
+   Notes: Some instruments come with a tip, e.g. that the banjo's first listed string isn't its lowest.
+*/
+fn draw_instrument(ui: &mut egui::Ui, settings: &mut Settings) {
+    let previous = settings.instrument;
+    egui::ComboBox::from_label("Instrument")
+        .selected_text(settings.instrument.label())
+        .show_ui(ui, |ui| {
+            for instrument in Instrument::ALL {
+                ui.selectable_value(&mut settings.instrument, instrument, instrument.label());
+            }
+        });
+    if settings.instrument != previous {
+        save_settings(settings);
+    }
+    if let Some(tip) = settings.instrument.tip() {
+        ui.label(tip);
+    }
+}
+
+/*
+   Purpose: Writes the settings to disk
+   Notes: Failing to save isn't worth interrupting the user over, the setting still applies for this session.
+*/
+fn save_settings(settings: &Settings) {
+    if let Err(err) = settings.save() {
+        eprintln!("could not save settings: {}", err);
+    }
+}
+
 /*
    Purpose: Draws the portion related to the "homepage" of the project
    Notes: This is just a little bit of information about how to use the application/what it's for
@@ -89,10 +263,11 @@ fn draw_home(ctx: &egui::Context) {
     egui::CentralPanel::default().show(ctx, |ui| {
         ui.heading("Whats this app for?");
         ui.add_space(SPACING);
-        ui.label("This application is meant to help you with guitar related tasks. Currently, it only has the capability to help with your guitar tuning.");
-        ui.label("You may choose to \"Tune by ear\" or \"Tune by recording\". ");
+        ui.label("This application is meant to help you with guitar related tasks. Currently, it only has the capability to help with your guitar tuning, though basses, ukuleles, mandolins, banjos and the violin family can be picked from the instrument list too.");
+        ui.label("You may choose to \"Tune by ear\", \"Tune by recording\" or \"Tune string to string\". ");
         ui.label("\"Tune by ear\" lets you play specific pitches so you can tune your guitar strings accordingly by the sound.");
         ui.label("\"Tune by recording\" lets you record your guitar strings making noise. The application will then tell you how close your string is to the proper pitch.");
+        ui.label("\"Tune string to string\" walks you through tuning each string against the one below it, with the 5th fret or harmonic methods.");
         ui.label(
             "Note: Try to use the \"Tune by ear\" feature to get your guitar string as close as possible to the proper pitch. Extreme variance will cause inconsistent behavior with the \"Tune by recording\" algorithm",
         );
@@ -102,551 +277,1022 @@ fn draw_home(ctx: &egui::Context) {
 
 /*
    Purpose: This function displays the "Tune by ear" page of the application
-   Notes: The tunings listed here are some of the common tunings found on https://muted.io/guitar-tuning-chart/ and https://theacousticguitarist.com/alternate-tunings-for-acoustic-guitar/
+   Notes: The tunings themselves come from the tuning module.
+          If a note can't be played, e.g. because the output device was unplugged, the error stays on the page until it's retried or dismissed.
+          Tones play in the background, and pressing another note cuts off the one that's playing.
+          "Play all" plays a tuning's strings one at a time or strums them, so a whole tuning can be checked in one go.
+          With "Drone" ticked a tone keeps going until it's stopped, so the string can be tuned while it sounds.
+          The volume is in dBFS, where 0 is a full scale sine. The player's limiter stops anything from going past that.
 */
-fn draw_tune_by_ear(ctx: &egui::Context, volume: &mut i32) {
+fn draw_tune_by_ear(
+    ctx: &egui::Context,
+    tunings: &[&Tuning],
+    tone_player: &mut TonePlayer,
+    settings: &mut Settings,
+    output_choices: &mut OutputChoices,
+    playback_error: &mut Option<(Playback, GuitarToolsError)>,
+) {
     egui::CentralPanel::default().show(ctx, |ui| {
-        ui.heading("Make sure to turn your SYSTEM volume down! This can be quite loud");
+        ui.heading("Check your SYSTEM volume before playing a note!");
         ui.label("Press one of the notes to have it be played out loud.");
         ui.label(
-            "Notes are listed as: [Note] or [Note]_S or [Note]_F for sharp and flat respectively.",
-        );
-        ui.label(
-            "If two notes are the same in a tuning, undercase denotes that it is the higher note.",
+            "Notes are listed with their octave, e.g. on a guitar E2 is the low E string and E4 is the high one. A b means flat and a # means sharp.",
         );
         ui.add_space(SPACING);
         ui.label("Volume:");
-        ui.add(egui::Slider::new(volume, 0..=100));
-        ui.add_space(SPACING);
-        //Standard Tuning -------------------------------------------------------------------------
-        ui.label("Standard Tuning:");
-        //This allows the buttons to be horizontally placed left to right
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-            if ui.button("E").clicked() {
-                play_note(Note::E2, *volume);
-            }
-            if ui.button("A").clicked() {
-                play_note(Note::A2, *volume);
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::Slider::new(&mut settings.volume_db, VOLUME_RANGE_DB)
+                    .suffix(" dBFS")
+                    .fixed_decimals(0),
+            );
+            if response.changed() {
+                tone_player.set_volume_db(settings.volume_db);
             }
-            if ui.button("D").clicked() {
-                play_note(Note::D3, *volume);
+            if response.drag_released() || (response.changed() && !response.dragged()) {
+                save_settings(settings);
             }
-            if ui.button("G").clicked() {
-                play_note(Note::G3, *volume);
+            let mut drone = tone_player.drone();
+            if ui.checkbox(&mut drone, "Drone").changed() {
+                tone_player.set_drone(drone);
             }
-            if ui.button("B").clicked() {
-                play_note(Note::B3, *volume);
+            let playing = tone_player.playing();
+            if ui
+                .add_enabled(!playing.is_empty(), egui::Button::new("Stop"))
+                .clicked()
+            {
+                tone_player.stop();
             }
-            if ui.button("e").clicked() {
-                play_note(Note::E4, *volume);
+            if !playing.is_empty() {
+                let pitches: Vec<String> = playing.iter().map(Pitch::to_string).collect();
+                ui.label(format!("Playing {}", pitches.join(" ")));
+                ctx.request_repaint_after(PLAYBACK_REPAINT_INTERVAL);
             }
         });
-        ui.add_space(SPACING);
-
-        //Half Step Down --------------------------------------------------------------------------
-        ui.label("Half Step Down Tuning:");
-        //This allows the buttons to be horizontally placed left to right
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-            if ui.button("E_F").clicked() {
-                play_note(Note::E2F, *volume);
-            }
-            if ui.button("A_F").clicked() {
-                play_note(Note::A2F, *volume);
-            }
-            if ui.button("D_F").clicked() {
-                play_note(Note::D3F, *volume);
-            }
-            if ui.button("G_F").clicked() {
-                play_note(Note::G3F, *volume);
-            }
-            if ui.button("B_F").clicked() {
-                play_note(Note::B3F, *volume);
-            }
-            if ui.button("e_F").clicked() {
-                play_note(Note::E4F, *volume);
+        if draw_tone_settings(ui, settings) {
+            save_settings(settings);
+        }
+        if draw_sequence_settings(ui, settings) {
+            save_settings(settings);
+        }
+        if draw_output_settings(ui, settings, output_choices) {
+            save_settings(settings);
+        }
+        let mut pressed = None;
+        if let Some((playback, err)) = playback_error {
+            match draw_error(ui, &format!("Could not play {}: {}", playback, err)) {
+                Some(ErrorAction::Retry) => pressed = Some(playback.clone()),
+                Some(ErrorAction::Dismiss) => *playback_error = None,
+                None => {}
             }
-        });
+        }
         ui.add_space(SPACING);
-
-        //Full Step Down --------------------------------------------------------------------------
-        ui.label("Full Step Down Tuning:");
-        //This allows the buttons to be horizontally placed left to right
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-            if ui.button("D").clicked() {
-                play_note(Note::D2, *volume);
-            }
-            if ui.button("G").clicked() {
-                play_note(Note::G2, *volume);
-            }
-            if ui.button("C").clicked() {
-                play_note(Note::C3, *volume);
-            }
-            if ui.button("F").clicked() {
-                play_note(Note::F3, *volume);
-            }
-            if ui.button("A").clicked() {
-                play_note(Note::A3, *volume);
-            }
-            if ui.button("d").clicked() {
-                play_note(Note::D4, *volume);
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for tuning in tunings {
+                match draw_tuning(ui, tuning, true) {
+                    Some(TuningButton::String(pitch)) => pressed = Some(Playback::Note(pitch)),
+                    Some(TuningButton::PlayAll) => pressed = Some(Playback::All((*tuning).clone())),
+                    None => {}
+                }
             }
         });
-        ui.add_space(SPACING);
+        if let Some(playback) = pressed {
+            let output_device = settings.output_device.as_deref();
+            let result = match &playback {
+                Playback::Note(pitch) => {
+                    tone_player.play(*pitch, settings.reference_a4, settings.tone, output_device)
+                }
+                Playback::All(tuning) => tone_player.play_all(
+                    &tuning.strings,
+                    settings.reference_a4,
+                    settings.tone,
+                    settings.sequence,
+                    output_device,
+                ),
+            };
+            *playback_error = result.err().map(|err| (playback, err));
+        }
+    });
+}
 
-        //Drop D Tuning ---------------------------------------------------------------------------
-        ui.label("Drop D Tuning:");
-        //This allows the buttons to be horizontally placed left to right
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-            if ui.button("D").clicked() {
-                play_note(Note::D2, *volume);
-            }
-            if ui.button("A").clicked() {
-                play_note(Note::A2, *volume);
-            }
-            if ui.button("d").clicked() {
-                play_note(Note::D3, *volume);
-            }
-            if ui.button("G").clicked() {
-                play_note(Note::G3, *volume);
-            }
-            if ui.button("B").clicked() {
-                play_note(Note::B3, *volume);
-            }
-            if ui.button("E").clicked() {
-                play_note(Note::E4, *volume);
+/*
+   Purpose: What the "Tune by ear" page was asked to play, either one string or a whole tuning with "Play all"
+*/
+#[derive(Clone)]
+enum Playback {
+    Note(Pitch),
+    All(Tuning),
+}
+
+impl std::fmt::Display for Playback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Playback::Note(pitch) => write!(f, "{}", pitch),
+            Playback::All(tuning) => write!(f, "{}", tuning.name),
+        }
+    }
+}
+
+/*
+   Purpose: Draws how "Play all" plays a tuning: one string at a time or strummed, from which end, and the gap between strings
+   Notes: Returns true when the settings should be saved. The gap only applies to one string at a time, a strum is always quick.
+*/
+fn draw_sequence_settings(ui: &mut egui::Ui, settings: &mut Settings) -> bool {
+    let sequence = &mut settings.sequence;
+    let previous = (sequence.style, sequence.order);
+    let mut save = false;
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_label("Play all")
+            .selected_text(sequence.style.label())
+            .show_ui(ui, |ui| {
+                for style in SequenceStyle::ALL {
+                    ui.selectable_value(&mut sequence.style, style, style.label());
+                }
+            });
+        egui::ComboBox::from_id_source("Play all order")
+            .selected_text(sequence.order.label())
+            .show_ui(ui, |ui| {
+                for order in SequenceOrder::ALL {
+                    ui.selectable_value(&mut sequence.order, order, order.label());
+                }
+            });
+        if sequence.style == SequenceStyle::Arpeggio {
+            let response = ui.add(
+                egui::Slider::new(&mut sequence.gap, GAP_RANGE)
+                    .text("Gap")
+                    .suffix(" s")
+                    .fixed_decimals(1),
+            );
+            save = response.drag_released() || (response.changed() && !response.dragged());
+        }
+    });
+    save || (sequence.style, sequence.order) != previous
+}
+
+/*
+   Purpose: Draws the timbre picker, and the brightness and decay sliders when the plucked string is picked
+   Notes: Returns true when the settings should be saved, which for the sliders is once the user lets go of them.
+          The new sound is heard from the next note, the one that's playing carries on as it was.
+*/
+fn draw_tone_settings(ui: &mut egui::Ui, settings: &mut Settings) -> bool {
+    let tone = &mut settings.tone;
+    let previous = tone.timbre;
+    egui::ComboBox::from_label("Sound")
+        .selected_text(tone.timbre.label())
+        .show_ui(ui, |ui| {
+            for timbre in Timbre::ALL {
+                ui.selectable_value(&mut tone.timbre, timbre, timbre.label());
             }
         });
-        ui.add_space(SPACING);
+    let mut save = tone.timbre != previous;
 
-        //Open E Tuning ---------------------------------------------------------------------------
-        ui.label("Open E Tuning:");
-        //This allows the buttons to be horizontally placed left to right
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-            if ui.button("E").clicked() {
-                play_note(Note::E2, *volume);
-            }
-            if ui.button("B").clicked() {
-                play_note(Note::B2, *volume);
-            }
-            if ui.button("e").clicked() {
-                play_note(Note::E3, *volume);
-            }
-            if ui.button("G_S").clicked() {
-                play_note(Note::G3S, *volume);
-            }
-            if ui.button("b").clicked() {
-                play_note(Note::B3, *volume);
+    if tone.timbre == Timbre::Plucked {
+        let brightness = ui.add(
+            egui::Slider::new(&mut tone.brightness, BRIGHTNESS_RANGE)
+                .text("Brightness")
+                .fixed_decimals(2),
+        );
+        let decay = ui.add(
+            egui::Slider::new(&mut tone.decay, DECAY_RANGE)
+                .text("Decay")
+                .suffix(" s")
+                .fixed_decimals(1),
+        );
+        for response in [brightness, decay] {
+            save |= response.drag_released() || (response.changed() && !response.dragged());
+        }
+    }
+    save
+}
+
+/*
+   Purpose: The output devices offered on the "Tune by ear" page, looked up once rather than every frame
+*/
+#[derive(Default)]
+struct OutputChoices {
+    loaded: bool,
+    devices: Vec<String>,
+    error: Option<String>,
+}
+
+impl OutputChoices {
+    fn refresh(&mut self) {
+        self.loaded = true;
+        match output_devices() {
+            Ok(devices) => {
+                self.devices = devices;
+                self.error = None;
             }
-            if ui.button("e4").clicked() {
-                play_note(Note::E4, *volume);
+            Err(err) => {
+                self.devices = vec![];
+                self.error = Some(err.to_string());
             }
-        });
-        ui.add_space(SPACING);
+        }
+    }
+}
+
+/*
+   Purpose: Draws the output device picker. Returns true if the choice changed.
+   Notes: Tones are rendered at whatever rate the chosen device runs at, so there's no sample rate to pick here.
+*/
+fn draw_output_settings(
+    ui: &mut egui::Ui,
+    settings: &mut Settings,
+    output_choices: &mut OutputChoices,
+) -> bool {
+    if !output_choices.loaded {
+        output_choices.refresh();
+    }
+    let before = settings.output_device.clone();
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_label("Output device")
+            .selected_text(settings.output_device.as_deref().unwrap_or("Default"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.output_device, None, "Default");
+                for device in &output_choices.devices {
+                    ui.selectable_value(&mut settings.output_device, Some(device.clone()), device);
+                }
+            });
+        if ui.button("Refresh devices").clicked() {
+            output_choices.refresh();
+        }
     });
+    if let Some(err) = &output_choices.error {
+        ui.colored_label(
+            egui::Color32::RED,
+            format!("Could not list outputs: {}", err),
+        );
+    }
+    settings.output_device != before
 }
 
 /*
    Purpose: This draws the ui for the "Tune by recording" page
    Notes: It's recommended that any usere that is using this has their strings at least somewhat near the pitch, i.e. after tuning by ear.
 */
-fn draw_tune_by_recording(ctx: &egui::Context, tuning_result: &mut String) {
+fn draw_tune_by_recording(
+    ctx: &egui::Context,
+    tunings: &[&Tuning],
+    live_tuner: &mut LiveTuner,
+    settings: &mut Settings,
+    spectrogram_view: &mut widgets::SpectrogramView,
+    input_choices: &mut InputChoices,
+) {
     egui::CentralPanel::default().show(ctx, |ui| {
-        ui.heading("Instructions:");
-        ui.label(
-            "Play a string on your guitar and press the button which you would like to tune it to.",
-        );
-        ui.label(
-            "Your audio is recorded, and processed. After, the program will tell you how close you are to the proper pitch.",
-        );
-        ui.label(
-            "Try to use the \"Tune by ear\" feature to get your guitar string as close as possible. Extreme variance will cause inconsistent behavior with the algorithm",
-        );
-        ui.label(
-            "Use this as more of a confirmation that you have tuned properly.",
-        );
-        ui.add_space(SPACING);
-        ui.label(tuning_result.as_str());
-        ui.add_space(SPACING);
-        //Standard Tuning -------------------------------------------------------------------------
-        ui.label("Standard Tuning:");
-        //This allows the buttons to be horizontally placed left to right
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-            if ui.button("E").clicked() {
-                *tuning_result = tune_by_recording(Note::E2);
-            }
-            if ui.button("A").clicked() {
-                *tuning_result = tune_by_recording(Note::A2);
-            }
-            if ui.button("D").clicked() {
-                *tuning_result = tune_by_recording(Note::D3);
-            }
-            if ui.button("G").clicked() {
-                *tuning_result = tune_by_recording(Note::G3);
+        // The whole page scrolls, since the analysis view and a long list of tunings don't both fit
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.heading("Instructions:");
+            ui.label("Press \"Start listening\" and play a string on your guitar. Then press the button of the note which you would like to tune it to.");
+            ui.label(
+                "Your audio is continuously recorded and processed. The reading below updates several times a second and tells you how close you are to the proper pitch.",
+            );
+            ui.label(
+                "Try to use the \"Tune by ear\" feature to get your guitar string as close as possible. Extreme variance will cause inconsistent behavior with the algorithm",
+            );
+            ui.label(
+                "Use this as more of a confirmation that you have tuned properly.",
+            );
+            ui.label(
+                "If your tuning isn't listed, use the \"Chromatic\" button. It names whichever note it hears, along with how many cents off it is.",
+            );
+            ui.add_space(SPACING);
+            draw_listening(ctx, ui, live_tuner, settings);
+            draw_tuner_settings(ctx, ui, live_tuner, settings, input_choices);
+            let reading = live_tuner.latest_reading();
+            widgets::needle(ui, reading);
+            if settings.show_strobe {
+                widgets::strobe(ui, reading);
             }
-            if ui.button("B").clicked() {
-                *tuning_result = tune_by_recording(Note::B3);
+            ui.label(live_tuner.reading().as_str());
+            draw_analysis_view(ui, live_tuner, settings.reference_a4);
+            draw_spectrogram(ui, live_tuner, spectrogram_view, settings.reference_a4);
+            ui.add_space(SPACING);
+            //Chromatic -------------------------------------------------------------------------------
+            ui.label("Any Note:");
+            if ui.button("Chromatic").clicked() {
+                live_tuner.set_target(None);
             }
-            if ui.button("e").clicked() {
-                *tuning_result = tune_by_recording(Note::E4);
+            ui.add_space(SPACING);
+            for tuning in tunings {
+                if let Some(TuningButton::String(pitch)) = draw_tuning(ui, tuning, false) {
+                    live_tuner.set_target(Some(pitch));
+                }
             }
         });
-        ui.add_space(SPACING);
+    });
+}
 
-        //Half Step Down --------------------------------------------------------------------------
-        ui.label("Half Step Down Tuning:");
-        //This allows the buttons to be horizontally placed left to right
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-            if ui.button("E_F").clicked() {
-                *tuning_result = tune_by_recording(Note::E2F);
-            }
-            if ui.button("A_F").clicked() {
-                *tuning_result = tune_by_recording(Note::A2F);
-            }
-            if ui.button("D_F").clicked() {
-                *tuning_result = tune_by_recording(Note::D3F);
-            }
-            if ui.button("G_F").clicked() {
-                *tuning_result = tune_by_recording(Note::G3F);
-            }
-            if ui.button("B_F").clicked() {
-                *tuning_result = tune_by_recording(Note::B3F);
-            }
-            if ui.button("e_F").clicked() {
-                *tuning_result = tune_by_recording(Note::E4F);
+/*
+   Purpose: Draws the button that starts and stops listening, and why listening failed if it did
+   Notes: Both pages that listen to the microphone use this.
+*/
+fn draw_listening(
+    ctx: &egui::Context,
+    ui: &mut egui::Ui,
+    live_tuner: &mut LiveTuner,
+    settings: &Settings,
+) {
+    ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+        if live_tuner.is_listening() {
+            if ui.button("Stop listening").clicked() {
+                live_tuner.stop();
             }
-        });
-        ui.add_space(SPACING);
+        } else if ui.button("Start listening").clicked() {
+            start_listening(ctx, live_tuner, settings);
+        }
+    });
+    if let Some(err) = live_tuner.error() {
+        match draw_error(ui, &format!("Could not listen: {}", err)) {
+            Some(ErrorAction::Retry) => start_listening(ctx, live_tuner, settings),
+            Some(ErrorAction::Dismiss) => live_tuner.clear_error(),
+            None => {}
+        }
+    }
+}
 
-        //Full Step Down --------------------------------------------------------------------------
-        ui.label("Full Step Down Tuning:");
-        //This allows the buttons to be horizontally placed left to right
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-            if ui.button("D").clicked() {
-                *tuning_result = tune_by_recording(Note::D2);
-            }
-            if ui.button("G").clicked() {
-                *tuning_result = tune_by_recording(Note::G2);
-            }
-            if ui.button("C").clicked() {
-                *tuning_result = tune_by_recording(Note::C3);
-            }
-            if ui.button("F").clicked() {
-                *tuning_result = tune_by_recording(Note::F3);
-            }
-            if ui.button("A").clicked() {
-                *tuning_result = tune_by_recording(Note::A3);
-            }
-            if ui.button("d").clicked() {
-                *tuning_result = tune_by_recording(Note::D4);
-            }
-        });
-        ui.add_space(SPACING);
+/*
+   Purpose: Where the user has got to on the "Tune string to string" page
+   Notes: tuning is the name of the chosen tuning, None until one is picked (the first one for the instrument is used until then).
+          reference is the frequency heard when the user captured the current step's reference note.
+*/
+#[derive(Default)]
+struct RelativeGuide {
+    tuning: Option<String>,
+    method: RelativeMethod,
+    step: usize,
+    reference: Option<f64>,
+}
 
-        //Drop D Tuning ---------------------------------------------------------------------------
-        ui.label("Drop D Tuning:");
-        //This allows the buttons to be horizontally placed left to right
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-            if ui.button("D").clicked() {
-                *tuning_result = tune_by_recording(Note::D2);
-            }
-            if ui.button("A").clicked() {
-                *tuning_result = tune_by_recording(Note::A2);
-            }
-            if ui.button("d").clicked() {
-                *tuning_result = tune_by_recording(Note::D3);
-            }
-            if ui.button("G").clicked() {
-                *tuning_result = tune_by_recording(Note::G3);
-            }
-            if ui.button("B").clicked() {
-                *tuning_result = tune_by_recording(Note::B3);
-            }
-            if ui.button("E").clicked() {
-                *tuning_result = tune_by_recording(Note::E4);
-            }
-        });
-        ui.add_space(SPACING);
+impl RelativeGuide {
+    fn go_to(&mut self, step: usize) {
+        self.step = step;
+        self.reference = None;
+    }
+}
 
-        //Open E Tuning ---------------------------------------------------------------------------
-        ui.label("Open E Tuning:");
-        //This allows the buttons to be horizontally placed left to right
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-            if ui.button("E").clicked() {
-                *tuning_result = tune_by_recording(Note::E2);
-            }
-            if ui.button("B").clicked() {
-                *tuning_result = tune_by_recording(Note::B2);
-            }
-            if ui.button("e").clicked() {
-                *tuning_result = tune_by_recording(Note::E3);
-            }
-            if ui.button("G_S").clicked() {
-                *tuning_result = tune_by_recording(Note::G3S);
+/*
+   Purpose: This draws the "Tune string to string" page, which walks through tuning each string against the one below it
+   Notes: The steps come from the relative module. The reference note is heard and captured first, then the target note is
+          compared against it, so both notes are measured with the same microphone and detector.
+          The needle shows how far the target string is from where it should be, allowing for harmonics being pure intervals.
+*/
+fn draw_tune_relative(
+    ctx: &egui::Context,
+    tunings: &[&Tuning],
+    live_tuner: &mut LiveTuner,
+    settings: &mut Settings,
+    input_choices: &mut InputChoices,
+    guide: &mut RelativeGuide,
+) {
+    egui::CentralPanel::default().show(ctx, |ui| {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.heading("Instructions:");
+            ui.label("Tune your lowest string first, by ear or with \"Tune by recording\". Each of the other strings is then tuned against the one below it.");
+            ui.label("Press \"Start listening\", play the reference note and press \"Capture reference\" while it rings. Then play the string you're tuning and turn its peg until the needle sits in the middle.");
+            ui.label("The slower the two notes beat against each other, the closer they are. On a fretless instrument, stop the string where that fret would be.");
+            ui.add_space(SPACING);
+            draw_listening(ctx, ui, live_tuner, settings);
+            draw_tuner_settings(ctx, ui, live_tuner, settings, input_choices);
+            ui.add_space(SPACING);
+
+            let tuning = match guide
+                .tuning
+                .as_ref()
+                .and_then(|name| tunings.iter().find(|tuning| &tuning.name == name))
+                .or_else(|| tunings.first())
+            {
+                Some(tuning) => *tuning,
+                None => {
+                    ui.label("There are no tunings for this instrument.");
+                    return;
+                }
+            };
+            let (previous_tuning, previous_method) = (tuning.name.clone(), guide.method);
+            let mut chosen_tuning = previous_tuning.clone();
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Tuning")
+                    .selected_text(&chosen_tuning)
+                    .show_ui(ui, |ui| {
+                        for tuning in tunings {
+                            ui.selectable_value(&mut chosen_tuning, tuning.name.clone(), &tuning.name);
+                        }
+                    });
+                egui::ComboBox::from_label("Method")
+                    .selected_text(guide.method.label())
+                    .show_ui(ui, |ui| {
+                        for method in RelativeMethod::ALL {
+                            ui.selectable_value(&mut guide.method, method, method.label());
+                        }
+                    });
+            });
+            if chosen_tuning != previous_tuning || guide.method != previous_method {
+                guide.go_to(0);
             }
-            if ui.button("b").clicked() {
-                *tuning_result = tune_by_recording(Note::B3);
+            let tuning = tunings
+                .iter()
+                .find(|tuning| tuning.name == chosen_tuning)
+                .map_or(tuning, |tuning| *tuning);
+            guide.tuning = Some(chosen_tuning);
+
+            let steps = relative_steps(tuning, guide.method);
+            if steps.is_empty() {
+                ui.label("This tuning only has one string, so there's nothing to tune it against.");
+                return;
             }
-            if ui.button("e4").clicked() {
-                *tuning_result = tune_by_recording(Note::E4);
+            let index = guide.step.min(steps.len() - 1);
+            let step = steps[index];
+            // The analysis window is sized for whichever note is being listened for, which can be an octave apart
+            let listening_for = match guide.reference {
+                None => step.reference.pitch(),
+                Some(_) => step.target.pitch(),
+            };
+            live_tuner.set_target(Some(listening_for));
+
+            ui.heading(format!(
+                "Step {} of {}: tune the {} string",
+                index + 1,
+                steps.len(),
+                step.target.open
+            ));
+            let latest = live_tuner.latest_reading();
+            match guide.reference {
+                None => {
+                    ui.label(format!("1. Play {} ({}).", step.reference, step.reference.pitch()));
+                    match latest {
+                        Some(reading) => ui.label(format!("Hearing {:.2} Hz", reading.frequency)),
+                        None => ui.label("Hearing nothing clear yet"),
+                    };
+                    if ui
+                        .add_enabled(latest.is_some(), egui::Button::new("Capture reference"))
+                        .clicked()
+                    {
+                        guide.reference = latest.map(|reading| reading.frequency);
+                    }
+                    widgets::needle(ui, None);
+                }
+                Some(reference) => {
+                    ui.label(format!(
+                        "1. {} was heard at {:.2} Hz.",
+                        step.reference, reference
+                    ));
+                    ui.label(format!("2. Now play {} ({}).", step.target, step.target.pitch()));
+                    let compared = latest.map(|reading| step.compare(reference, reading.frequency));
+                    match compared {
+                        Some(compared) => ui.label(format!(
+                            "{:+.1} cents, beating {:.2} times a second: {}",
+                            compared.cents,
+                            compared.beat_frequency,
+                            advice(compared.cents)
+                        )),
+                        None => ui.label("Hearing nothing clear yet"),
+                    };
+                    if ui.button("Capture reference again").clicked() {
+                        guide.reference = None;
+                    }
+                    widgets::needle(
+                        ui,
+                        latest.zip(compared).map(|(reading, compared)| TunerReading {
+                            pitch: step.target.open,
+                            cents: compared.cents,
+                            ..reading
+                        }),
+                    );
+                }
             }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(index > 0, egui::Button::new("Previous string"))
+                    .clicked()
+                {
+                    guide.go_to(index - 1);
+                }
+                if ui
+                    .add_enabled(index + 1 < steps.len(), egui::Button::new("Next string"))
+                    .clicked()
+                {
+                    guide.go_to(index + 1);
+                }
+            });
         });
-        ui.add_space(SPACING);
     });
 }
 
-#[derive(PartialEq)]
-enum AppModeOptions {
-    Home,
-    TuneByEar,
-    TuneByRecording,
-}
-
-// SOUND ------------------------------------------------------------------------------------------------------------------------
 /*
-   Purpose: Holds the values in Hz for guitar notes.
-   Notes: Based on information from https://fretsuccess.com/what-are-the-guitar-string-frequencies/ and https://en.wikipedia.org/wiki/Scientific_pitch_notation
+   Purpose: Which of a tuning's buttons was pressed
 */
-#[derive(Copy, Clone)]
-enum Note {
-    //Standard Tunings
-    E2 = 82,
-    A2 = 110,
-    D3 = 147,
-    G3 = 196,
-    B3 = 247,
-    E4 = 330,
-
-    E2F = 78,
-    A2F = 104,
-    D3F = 138,
-    G3F = 185,
-    B3F = 233,
-    E4F = 311,
-
-    D2 = 73,
-    G2 = 98,
-    C3 = 131,
-    F3 = 175,
-    A3 = 220,
-    D4 = 294,
-
-    B2 = 123,
-    E3 = 165,
-    G3S = 208,
+enum TuningButton {
+    String(Pitch),
+    PlayAll,
 }
 
 /*
-   Purpose: Plays a note of the given frequency for one second
-   Notes: Based on documentation: https://docs.rs/rodio/latest/rodio/
+   Purpose: Draws one tuning as a row of buttons, one per string, and returns whichever was pressed
+   Notes: Both the "Tune by ear" and "Tune by recording" pages use this, so they always list the same tunings.
+          With play_all a "Play all" button ends the row.
 */
-fn play_note(frequency: Note, volume: i32) {
-    let sample_rate: u32 = 48000;
-    let duration: u32 = 1;
-    //Casts enum to f32
-    let frequency = frequency as i16 as f32;
-
-    let mut source: Vec<f32> = vec![];
-
-    //Builds Note audio
-    for t in (0..(sample_rate * duration)).map(|x| x as f32 / sample_rate as f32) {
-        let sample = (t * frequency * 2.0 * PI).sin();
-
-        let wave = sample * volume as f32 * VOLUME_SCALER;
-        source.push(wave);
+fn draw_tuning(ui: &mut egui::Ui, tuning: &Tuning, play_all: bool) -> Option<TuningButton> {
+    let mut pressed = None;
+    if tuning.is_reentrant() {
+        ui.label(format!("{} (re-entrant):", tuning.name));
+    } else {
+        ui.label(format!("{}:", tuning.name));
     }
+    //This allows the buttons to be horizontally placed left to right
+    ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+        for pitch in &tuning.strings {
+            if ui.button(pitch.to_string()).clicked() {
+                pressed = Some(TuningButton::String(*pitch));
+            }
+        }
+        if play_all && ui.button("Play all").clicked() {
+            pressed = Some(TuningButton::PlayAll);
+        }
+    });
+    ui.add_space(SPACING);
+    pressed
+}
 
-    //For playing audio
-    // Based on https://docs.rs/rodio/latest/rodio/ and https://docs.rs/rodio/latest/src/rodio/buffer.rs.html
-    // Get a output stream handle to the default physical sound device
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
+/*
+   Purpose: What the user asked to do about an error
+*/
+enum ErrorAction {
+    Retry,
+    Dismiss,
+}
 
-    //Creating the "Source" that rodio needs to play sounds
-    let source: SamplesBuffer<f32> = SamplesBuffer::new(1, sample_rate, source);
+/*
+   Purpose: Draws an error message with buttons to retry whatever failed or hide the message
+   Notes: Most audio errors are fixed by plugging something in or picking another device, so retrying is offered rather than giving up.
+*/
+fn draw_error(ui: &mut egui::Ui, message: &str) -> Option<ErrorAction> {
+    let mut action = None;
+    ui.colored_label(egui::Color32::RED, message);
+    ui.horizontal(|ui| {
+        if ui.button("Retry").clicked() {
+            action = Some(ErrorAction::Retry);
+        }
+        if ui.button("Dismiss").clicked() {
+            action = Some(ErrorAction::Dismiss);
+        }
+    });
+    action
+}
 
-    let sink = Sink::try_new(&stream_handle).unwrap();
+fn start_listening(ctx: &egui::Context, live_tuner: &mut LiveTuner, settings: &Settings) {
+    let ctx = ctx.clone();
+    live_tuner.start(settings.input.clone(), move || ctx.request_repaint());
+}
 
-    // Play the sound directly on the device
-    sink.append(source);
+/*
+   Purpose: Draws the collapsible settings panel on the "Tune by recording" page
+   Notes: Changing the detector takes effect on the next reading, so users can flip between them while playing the same string to compare.
+          The chosen detector is saved with the rest of the settings.
+*/
+fn draw_tuner_settings(
+    ctx: &egui::Context,
+    ui: &mut egui::Ui,
+    live_tuner: &mut LiveTuner,
+    settings: &mut Settings,
+    input_choices: &mut InputChoices,
+) {
+    egui::CollapsingHeader::new("Settings").show(ui, |ui| {
+        let previous_detector = settings.detector;
+        egui::ComboBox::from_label("Pitch detector")
+            .selected_text(settings.detector.label())
+            .show_ui(ui, |ui| {
+                for kind in DetectorKind::ALL {
+                    ui.selectable_value(&mut settings.detector, kind, kind.label());
+                }
+            });
+        if settings.detector != previous_detector {
+            live_tuner.set_detector(settings.detector);
+            save_settings(settings);
+        }
+        if ui
+            .checkbox(&mut settings.show_strobe, "Show strobe for fine tuning")
+            .changed()
+        {
+            save_settings(settings);
+        }
+        ui.separator();
+        if draw_input_settings(ui, settings, input_choices) {
+            save_settings(settings);
+            if live_tuner.is_listening() {
+                start_listening(ctx, live_tuner, settings);
+            }
+        }
+    });
+}
 
-    // The sound plays in a separate thread. This call will block the current thread until the sink
-    // has finished playing all its queued sounds.
-    sink.sleep_until_end();
+/*
+   Purpose: The hosts, devices and formats offered in the input settings
+   Notes: Asking the system for devices can take a moment, so they're looked up when the panel is first shown and
+          whenever the host or device changes, rather than on every frame. "Refresh devices" picks up newly plugged in ones.
+*/
+#[derive(Default)]
+struct InputChoices {
+    loaded: bool,
+    hosts: Vec<String>,
+    devices: Vec<String>,
+    configs: Vec<InputConfig>,
+    error: Option<String>,
 }
 
-// Audio Recording --------------------------------------------------------------------------------------------------------------
-// This portion is based on the "cpal:recording" section of https://www.youtube.com/watch?v=ZweInbMBsa4
-struct AudioData {
-    samples: Vec<f32>,
+impl InputChoices {
+    fn refresh(&mut self, settings: &Settings) {
+        let host = settings.input.host.as_deref();
+        let device = settings.input.device.as_deref();
+        self.loaded = true;
+        self.hosts = input_hosts();
+        self.error = None;
+        self.devices = input_devices(host).unwrap_or_else(|err| {
+            self.error = Some(err.to_string());
+            vec![]
+        });
+        self.configs = input_configs(host, device).unwrap_or_else(|err| {
+            self.error = Some(err.to_string());
+            vec![]
+        });
+    }
 }
 
 /*
-   Purpose: This is intended to record a vibrating guitar string and return the recorded audio so it can be processed.
-   Notes: This is based on https://docs.rs/cpal/0.15.2/cpal/, https://github.com/RustAudio/cpal/blob/master/examples/record_wav.rs#L129 and https://www.youtube.com/watch?v=ZweInbMBsa4
+   Purpose: Draws the pickers for the audio host, input device, format and channel. Returns true if any of them changed.
+   Notes: Picking a new host or device puts the choices below it back to their defaults, since they may not exist on the new one.
 */
-fn obtain_audio() -> Result<AudioData, Box<dyn Error>> {
-    // We need to get the default audio devices/configs
-    // Currently it just panics if that doesn't work. That chnage will come later but my goal is getting the prototype to work right now.
-
-    let host = cpal::default_host();
-    let device = host
-        .default_input_device()
-        .expect("no input device available");
-
-    let mut supported_configs_range = device
-        .supported_input_configs()
-        .expect("error while querying configs");
-
-    let supported_config = supported_configs_range
-        .next()
-        .expect("no supported config?!")
-        .with_max_sample_rate();
-
-    // Making the structure that will store the recording
-    let clip = AudioData {
-        samples: Vec::new(),
-    };
+fn draw_input_settings(
+    ui: &mut egui::Ui,
+    settings: &mut Settings,
+    input_choices: &mut InputChoices,
+) -> bool {
+    if !input_choices.loaded {
+        input_choices.refresh(settings);
+    }
+    let before = settings.input.clone();
+    let input = &mut settings.input;
 
-    let clip = Arc::new(Mutex::new(Some(clip)));
-    let clip_2 = clip.clone();
-
-    type ClipHandler = Arc<Mutex<Option<AudioData>>>;
-    let channels = supported_config.channels();
-    /*
-       Notes: These were taken from https://github.com/RustAudio/cpal/blob/master/examples/record_wav.rs#L129 and https://www.youtube.com/watch?v=ZweInbMBsa4
-    */
-    fn write_input_data<T>(input: &[T], channels: u16, writer: &ClipHandler)
-    where
-        T: cpal::Sample,
-    {
-        if let Ok(mut guard) = writer.try_lock() {
-            if let Some(clip) = guard.as_mut() {
-                for frame in input.chunks(channels.into()) {
-                    clip.samples.push(frame[0].to_f32());
-                }
+    ui.label("Input:");
+    egui::ComboBox::from_label("Audio host")
+        .selected_text(input.host.as_deref().unwrap_or("Default"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut input.host, None, "Default");
+            for host in &input_choices.hosts {
+                ui.selectable_value(&mut input.host, Some(host.clone()), host);
             }
-        }
+        });
+    if input.host != before.host {
+        input.device = None;
     }
 
-    let err_fn = move |err| {
-        eprintln!("an error occurred on stream: {}", err);
-    };
+    egui::ComboBox::from_label("Input device")
+        .selected_text(input.device.as_deref().unwrap_or("Default"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut input.device, None, "Default");
+            for device in &input_choices.devices {
+                ui.selectable_value(&mut input.device, Some(device.clone()), device);
+            }
+        });
+    if input.device != before.device {
+        input.config = None;
+        input.channel = InputChannel::default();
+    }
+
+    egui::ComboBox::from_label("Format")
+        .selected_text(
+            input
+                .config
+                .map_or("Device default".to_string(), |config| config.label()),
+        )
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut input.config, None, "Device default");
+            for config in &input_choices.configs {
+                ui.selectable_value(&mut input.config, Some(*config), config.label());
+            }
+        });
 
-    //Does the writing based on the device data type
-    let stream = match supported_config.sample_format() {
-        cpal::SampleFormat::F32 => device.build_input_stream(
-            &supported_config.into(),
-            move |data, _: &_| write_input_data::<f32>(data, channels, &clip_2),
-            err_fn,
-        )?,
-        cpal::SampleFormat::I16 => device.build_input_stream(
-            &supported_config.into(),
-            move |data, _: &_| write_input_data::<i16>(data, channels, &clip_2),
-            err_fn,
-        )?,
-        cpal::SampleFormat::U16 => device.build_input_stream(
-            &supported_config.into(),
-            move |data, _: &_| write_input_data::<u16>(data, channels, &clip_2),
-            err_fn,
-        )?,
+    // Without a chosen format the channel count isn't known until the stream opens, so every channel the device has is offered.
+    // If even that isn't known, the saved channel is left alone rather than thrown away.
+    let channels = match input.config {
+        Some(config) => Some(config.channels),
+        None => input_choices
+            .configs
+            .iter()
+            .map(|config| config.channels)
+            .max(),
     };
+    if let (Some(channels), InputChannel::Channel(index)) = (channels, input.channel) {
+        if index >= channels {
+            input.channel = InputChannel::default();
+        }
+    }
+    let offered = channels.unwrap_or(match input.channel {
+        InputChannel::Channel(index) => index + 1,
+        InputChannel::Mixdown => 1,
+    });
+    egui::ComboBox::from_label("Channel")
+        .selected_text(input.channel.label())
+        .show_ui(ui, |ui| {
+            for index in 0..offered {
+                let channel = InputChannel::Channel(index);
+                ui.selectable_value(&mut input.channel, channel, channel.label());
+            }
+            ui.selectable_value(
+                &mut input.channel,
+                InputChannel::Mixdown,
+                InputChannel::Mixdown.label(),
+            );
+        });
 
-    //Starts the recording, and keeps it going for one second
-    stream.play()?;
-    std::thread::sleep(std::time::Duration::from_secs(1));
-    drop(stream);
-    let clip = clip.lock().unwrap().take().unwrap();
+    let refresh_clicked = ui.button("Refresh devices").clicked();
+    if let Some(err) = &input_choices.error {
+        ui.colored_label(
+            egui::Color32::RED,
+            format!("Could not list inputs: {}", err),
+        );
+    }
 
-    Ok(clip)
+    let changed = settings.input != before;
+    if refresh_clicked
+        || settings.input.host != before.host
+        || settings.input.device != before.device
+    {
+        input_choices.refresh(settings);
+    }
+    changed
 }
 
-// Sound Analysis ---------------------------------------------------------------------------------------------------------------
-
 /*
-   Purpose: This takes in a vector representing a signal waveform, performs a fourier transform and outputs the spectrogram (real normalized not complex)
-   Notes: This section is based on the documents in https://docs.rs/realfft/3.3.0/realfft/ and took inspiration from the source code at https://docs.rs/audioviz/latest/src/audioviz/fft.rs.html#55-62
+   Purpose: Draws the collapsible analysis view with the live waveform and spectrum
+   Notes: The listening thread only builds the plots while this is open, since it costs an extra fourier transform per reading.
 */
-fn generate_fourier_transform(audio: AudioData) -> Vec<f64> {
-    // We need to converet the samples into f64
-    let mut source: Vec<f64> = Vec::new();
-    for i in &audio.samples {
-        source.push(*i as f64);
-    }
-
-    // make a planner
-    let mut real_planner = RealFftPlanner::<f64>::new();
+fn draw_analysis_view(ui: &mut egui::Ui, live_tuner: &LiveTuner, reference_a4: f64) {
+    let response = egui::CollapsingHeader::new("Analysis view").show(ui, |ui| {
+        match live_tuner.analysis_view() {
+            Some(view) => {
+                ui.label("Waveform:");
+                widgets::waveform_plot(ui, &view);
+                ui.label("Spectrum (fundamental in green, harmonics dashed in orange):");
+                widgets::spectrum_plot(ui, &view, reference_a4);
+            }
+            None => {
+                ui.label("Start listening to see the waveform and spectrum.");
+            }
+        }
+    });
+    live_tuner.set_visualise(response.body_returned.is_some());
+}
 
-    // create a FFT
-    let r2c = real_planner.plan_fft_forward(source.len());
+/*
+   Purpose: Draws the collapsible spectrogram, which shows how a note's partials fade and drift over time
+   Notes: Like the analysis view, the listening thread only keeps the spectrogram going while this is open.
+*/
+fn draw_spectrogram(
+    ui: &mut egui::Ui,
+    live_tuner: &LiveTuner,
+    spectrogram_view: &mut widgets::SpectrogramView,
+    reference_a4: f64,
+) {
+    let response = egui::CollapsingHeader::new("Spectrogram").show(ui, |ui| {
+        spectrogram_view.controls(ui);
+        if live_tuner
+            .with_spectrogram(|spectrogram| spectrogram_view.show(ui, spectrogram, reference_a4))
+            .is_none()
+        {
+            ui.label("Start listening to see the spectrogram.");
+        }
+    });
+    live_tuner.set_spectrogram(response.body_returned.map(|_| spectrogram_view.settings));
+}
 
-    // make a vector for storing the spectrum
-    let mut spectrum = r2c.make_output_vec();
+// TUNING EDITOR ----------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: The custom tunings as they are being edited, before they are saved
+   Notes: Strings are kept as the text the user typed, so a half typed note like "E" doesn't get thrown away.
+          back_up is set when the file didn't load cleanly, so the first save moves it aside instead of losing what was left out.
+*/
+struct TuningEditor {
+    drafts: Vec<TuningDraft>,
+    message: Option<String>,
+    back_up: bool,
+}
 
-    // forward transform the signal
-    r2c.process(&mut source, &mut spectrum).unwrap();
+struct TuningDraft {
+    name: String,
+    instrument: Instrument,
+    strings: Vec<String>,
+}
 
-    let mut data: Vec<f64> = Vec::new();
+impl From<&Tuning> for TuningDraft {
+    fn from(tuning: &Tuning) -> Self {
+        Self {
+            name: tuning.name.clone(),
+            instrument: tuning.instrument,
+            strings: tuning
+                .strings
+                .iter()
+                .map(|pitch| pitch.to_string())
+                .collect(),
+        }
+    }
+}
 
-    //Normalizes complex to real
-    //Theoretically the index at our desired frequency should have the highest magnitude.
-    for i in &spectrum {
-        data.push(i.norm());
+impl TuningDraft {
+    fn parse(&self) -> Result<Tuning, String> {
+        Tuning::parse(&self.name, self.instrument, &self.strings).map_err(|err| err.to_string())
     }
-    data
 }
 
-// Putting Note Call to Frequency output all together ---------------------------------------------------------------------------
+enum EditorAction {
+    MoveUp(usize),
+    MoveDown(usize),
+    Delete(usize),
+}
+
 /*
-   Purpose: Finds the greatest absolute value in the given vector and outputs the index for it
-   Notes: In theory, the outputted index should be the most prominent frequency in the spectrogram
-          During testing, it seems that the second and third harmonics keep getting picked up. For example, a perfectly tuned E2 string (82 Hz) would pick up 164 Hz, 246 Hz etc.
-          My current solution to this is to limit where the data is gathered, but that assumes that the string is already somewhere in the ballpark of the pitch...
-          That's why there's a target note in here.
+   Purpose: Draws the "Edit tunings" page, where users make their own tunings
+   Notes: Returns the custom tunings whenever they've just been saved, so the tuning pages can pick them up.
+          Nothing is written until "Save" is pressed, and nothing is saved while any tuning has a problem.
 */
-fn find_greatest(data: Vec<f64>, target_note: Note) -> usize {
-    let mut index = 0;
-    let mut greatest: f64 = 0.0;
+fn draw_tuning_editor(ctx: &egui::Context, editor: &mut TuningEditor) -> Option<Vec<Tuning>> {
+    let mut saved = None;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.heading("Custom tunings:");
+        ui.label("Your tunings are listed after the built-in ones on the \"Tune by ear\" and \"Tune by recording\" pages.");
+        ui.label("Write each string as a note and octave, e.g. E2, Eb3 or F#3, in the order the strings sit on the instrument. They don't have to go from low to high.");
+        ui.add_space(SPACING);
+        ui.horizontal(|ui| {
+            if ui.button("New tuning").clicked() {
+                editor.drafts.push(new_draft(&editor.drafts));
+            }
+            if ui.button("Save").clicked() {
+                match save_drafts(&editor.drafts, &mut editor.back_up) {
+                    Ok((tunings, Some(backup))) => {
+                        editor.message = Some(format!(
+                            "Saved. The old file was kept as {}.",
+                            backup.display()
+                        ));
+                        saved = Some(tunings);
+                    }
+                    Ok((tunings, None)) => {
+                        editor.message = Some("Saved.".to_string());
+                        saved = Some(tunings);
+                    }
+                    Err(err) => editor.message = Some(format!("Not saved: {}", err)),
+                }
+            }
+        });
+        if let Some(message) = &editor.message {
+            ui.label(message.as_str());
+        }
+        ui.add_space(SPACING);
 
-    // This is technically bad because in theory someone could pass a target note of 4 -> 4 * 2 < 10, but none of the notes are currently like that.
-    // Minimum is currently 82, so subtracting the harmonic_guard is not a problem
-    let target_range = target_note as usize * 2 - HARMONIC_GUARD;
+        let mut action = None;
+        let count = editor.drafts.len();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (index, draft) in editor.drafts.iter_mut().enumerate() {
+                ui.push_id(index, |ui| {
+                    ui.group(|ui| {
+                        if let Some(chosen) = draw_draft(ui, draft, index, count) {
+                            action = Some(chosen);
+                        }
+                    });
+                });
+            }
+        });
 
-    for (i, x) in data.iter().enumerate() {
-        if i >= target_range {
-            break;
+        match action {
+            Some(EditorAction::MoveUp(index)) => editor.drafts.swap(index, index - 1),
+            Some(EditorAction::MoveDown(index)) => editor.drafts.swap(index, index + 1),
+            Some(EditorAction::Delete(index)) => {
+                editor.drafts.remove(index);
+            }
+            None => {}
+        }
+    });
+    saved
+}
+
+/*
+   Purpose: Draws the fields for one tuning in the editor, along with anything wrong with it
+*/
+fn draw_draft(
+    ui: &mut egui::Ui,
+    draft: &mut TuningDraft,
+    index: usize,
+    count: usize,
+) -> Option<EditorAction> {
+    let mut action = None;
+    ui.horizontal(|ui| {
+        ui.label("Name:");
+        ui.text_edit_singleline(&mut draft.name);
+        egui::ComboBox::from_id_source("instrument")
+            .selected_text(draft.instrument.label())
+            .show_ui(ui, |ui| {
+                for instrument in Instrument::ALL {
+                    ui.selectable_value(&mut draft.instrument, instrument, instrument.label());
+                }
+            });
+        if ui.add_enabled(index > 0, egui::Button::new("Up")).clicked() {
+            action = Some(EditorAction::MoveUp(index));
+        }
+        if ui
+            .add_enabled(index + 1 < count, egui::Button::new("Down"))
+            .clicked()
+        {
+            action = Some(EditorAction::MoveDown(index));
+        }
+        if ui.button("Delete").clicked() {
+            action = Some(EditorAction::Delete(index));
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Strings:");
+        for string in draft.strings.iter_mut() {
+            ui.add(egui::TextEdit::singleline(string).desired_width(40.0));
+        }
+        if ui
+            .add_enabled(
+                draft.strings.len() < *STRING_COUNT_RANGE.end(),
+                egui::Button::new("+"),
+            )
+            .clicked()
+        {
+            let next = draft
+                .strings
+                .last()
+                .cloned()
+                .unwrap_or_else(|| "E2".to_string());
+            draft.strings.push(next);
         }
-        if x.abs() > greatest.abs() {
-            greatest = data[i];
-            index = i;
+        if ui
+            .add_enabled(
+                draft.strings.len() > *STRING_COUNT_RANGE.start(),
+                egui::Button::new("-"),
+            )
+            .clicked()
+        {
+            draft.strings.pop();
         }
+    });
+    if let Err(err) = draft.parse() {
+        ui.colored_label(egui::Color32::RED, err);
     }
-    index
+    action
 }
 
 /*
-   Purpose: Records audio, runs it through a fourier transformation and determines the most prominent frequency. Then outputs a string telling the user whether they need to tune up or down
-   Notes: This will be sent to the gui to update the internal result string.
+   Purpose: Starts a new tuning off as standard guitar tuning with a name that isn't taken yet
 */
-fn tune_by_recording(note: Note) -> String {
-    let mut result = String::new();
-
-    let audio = obtain_audio().unwrap();
-    let spectrogram = generate_fourier_transform(audio);
-    let index = find_greatest(spectrogram, note);
-
-    let note = note as u32;
-    let index = index as u32;
-
-    result += "Result: (Target Pitch: ";
-    result += note.to_string().as_str();
-    result += " Hz Recorded Pitch: ";
-    result += index.to_string().as_str();
-    result += " Hz): ";
-
-    match note.cmp(&index) {
-        Ordering::Less => result += "\"You shoud loosen your string!\"",
-        Ordering::Greater => result += "\"You should tighen your string!\"",
-        Ordering::Equal => result += "\"Perfect!\"",
+fn new_draft(drafts: &[TuningDraft]) -> TuningDraft {
+    let mut number = drafts.len() + 1;
+    while drafts
+        .iter()
+        .any(|draft| draft.name.trim() == format!("My Tuning {}", number))
+    {
+        number += 1;
     }
+    let mut draft = TuningDraft::from(&built_in_tunings()[0]);
+    draft.name = format!("My Tuning {}", number);
+    draft
+}
 
-    result
+/*
+   Purpose: Saves the drafts as the custom tunings, moving the old file aside first if back_up is set
+   Notes: Returns the saved tunings, and where the old file went if it was moved.
+*/
+fn save_drafts(
+    drafts: &[TuningDraft],
+    back_up: &mut bool,
+) -> Result<(Vec<Tuning>, Option<PathBuf>), String> {
+    let tunings = drafts
+        .iter()
+        .enumerate()
+        .map(|(index, draft)| {
+            draft
+                .parse()
+                .map_err(|err| format!("tuning {}: {}", index + 1, err))
+        })
+        .collect::<Result<Vec<Tuning>, String>>()?;
+    validate_tunings(&tunings).map_err(|err| err.to_string())?;
+    let backup = match *back_up {
+        true => back_up_custom_tunings().map_err(|err| {
+            format!(
+                "the old file could not be moved to tunings.toml.bak: {}",
+                err
+            )
+        })?,
+        false => None,
+    };
+    *back_up = false;
+    save_custom_tunings(&tunings).map_err(|err| err.to_string())?;
+    Ok((tunings, backup))
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum AppModeOptions {
+    Home,
+    TuneByEar,
+    TuneByRecording,
+    TuneRelative,
+    EditTunings,
 }
@@ -0,0 +1,307 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{error::Error, fmt, str::FromStr};
+
+pub const STANDARD_A4: f64 = 440.0;
+const A4_MIDI: i32 = 69;
+const LOWEST_OCTAVE: i32 = -1;
+const HIGHEST_OCTAVE: i32 = 9;
+
+// NOTES ------------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: The seven letter names of the musical alphabet
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NoteName {
+    C,
+    D,
+    E,
+    F,
+    G,
+    A,
+    B,
+}
+
+impl NoteName {
+    /*
+       Purpose: How many semitones the natural note sits above C in the same octave
+    */
+    fn semitones_above_c(&self) -> i32 {
+        match self {
+            NoteName::C => 0,
+            NoteName::D => 2,
+            NoteName::E => 4,
+            NoteName::F => 5,
+            NoteName::G => 7,
+            NoteName::A => 9,
+            NoteName::B => 11,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Accidental {
+    Flat,
+    Natural,
+    Sharp,
+}
+
+impl Accidental {
+    fn semitones(&self) -> i32 {
+        match self {
+            Accidental::Flat => -1,
+            Accidental::Natural => 0,
+            Accidental::Sharp => 1,
+        }
+    }
+}
+
+/*
+   Purpose: A note in scientific pitch notation, e.g. E2 or Eb3
+   Notes: Based on https://en.wikipedia.org/wiki/Scientific_pitch_notation
+          The spelling is kept, so D#3 and Eb3 display differently even though they sound the same.
+          The octave number changes at C, so B#3 is the same as C4 and Cb4 is the same as B3.
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Pitch {
+    pub name: NoteName,
+    pub accidental: Accidental,
+    pub octave: i32,
+}
+
+impl Pitch {
+    pub const fn new(name: NoteName, accidental: Accidental, octave: i32) -> Self {
+        Self {
+            name,
+            accidental,
+            octave,
+        }
+    }
+
+    /*
+       Purpose: Builds a pitch from its MIDI note number (middle C, C4, is 60)
+       Notes: Black keys are spelled with sharps.
+    */
+    pub fn from_midi(midi: i32) -> Self {
+        const SPELLINGS: [(NoteName, Accidental); 12] = [
+            (NoteName::C, Accidental::Natural),
+            (NoteName::C, Accidental::Sharp),
+            (NoteName::D, Accidental::Natural),
+            (NoteName::D, Accidental::Sharp),
+            (NoteName::E, Accidental::Natural),
+            (NoteName::F, Accidental::Natural),
+            (NoteName::F, Accidental::Sharp),
+            (NoteName::G, Accidental::Natural),
+            (NoteName::G, Accidental::Sharp),
+            (NoteName::A, Accidental::Natural),
+            (NoteName::A, Accidental::Sharp),
+            (NoteName::B, Accidental::Natural),
+        ];
+        let (name, accidental) = SPELLINGS[midi.rem_euclid(12) as usize];
+        Self::new(name, accidental, midi.div_euclid(12) - 1)
+    }
+
+    pub fn midi(&self) -> i32 {
+        (self.octave + 1) * 12 + self.name.semitones_above_c() + self.accidental.semitones()
+    }
+
+    /*
+       Purpose: Gives the exact equal-tempered frequency in Hz, tuned so that A4 sits at reference_a4
+       Notes: Formula from https://en.wikipedia.org/wiki/Equal_temperament
+    */
+    pub fn frequency(&self, reference_a4: f64) -> f64 {
+        reference_a4 * 2f64.powf((self.midi() - A4_MIDI) as f64 / 12.0)
+    }
+
+    /*
+       Purpose: Finds the equal-tempered pitch closest to a frequency, along with how far off the frequency is from it in cents
+    */
+    pub fn nearest(frequency: f64, reference_a4: f64) -> (Self, f64) {
+        let midi = A4_MIDI as f64 + 12.0 * (frequency / reference_a4).log2();
+        let nearest = midi.round();
+        (Self::from_midi(nearest as i32), (midi - nearest) * 100.0)
+    }
+}
+
+impl fmt::Display for Pitch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let accidental = match self.accidental {
+            Accidental::Flat => "b",
+            Accidental::Natural => "",
+            Accidental::Sharp => "#",
+        };
+        write!(f, "{:?}{}{}", self.name, accidental, self.octave)
+    }
+}
+
+/*
+   Purpose: Why a piece of text couldn't be read as a pitch
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParsePitchError {
+    Empty,
+    UnknownNoteName(char),
+    MissingOctave,
+    InvalidOctave(String),
+}
+
+impl fmt::Display for ParsePitchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsePitchError::Empty => write!(f, "no note was given"),
+            ParsePitchError::UnknownNoteName(name) => {
+                write!(f, "\"{}\" is not a note name, expected A to G", name)
+            }
+            ParsePitchError::MissingOctave => {
+                write!(f, "the octave number is missing, e.g. the 2 in E2")
+            }
+            ParsePitchError::InvalidOctave(octave) => write!(
+                f,
+                "\"{}\" is not an octave between {} and {}",
+                octave, LOWEST_OCTAVE, HIGHEST_OCTAVE
+            ),
+        }
+    }
+}
+
+impl Error for ParsePitchError {}
+
+/*
+   Purpose: Reads a pitch such as "E2", "Eb3", "D#3" or "E♭3"
+   Notes: Flats can be written as "b" or "♭" and sharps as "#" or "♯". The note name may be lower case.
+*/
+impl FromStr for Pitch {
+    type Err = ParsePitchError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut chars = text.trim().chars().peekable();
+
+        let name = match chars.next().ok_or(ParsePitchError::Empty)? {
+            'C' | 'c' => NoteName::C,
+            'D' | 'd' => NoteName::D,
+            'E' | 'e' => NoteName::E,
+            'F' | 'f' => NoteName::F,
+            'G' | 'g' => NoteName::G,
+            'A' | 'a' => NoteName::A,
+            'B' | 'b' => NoteName::B,
+            other => return Err(ParsePitchError::UnknownNoteName(other)),
+        };
+
+        let accidental = match chars.peek() {
+            Some('b') | Some('♭') => Accidental::Flat,
+            Some('#') | Some('♯') => Accidental::Sharp,
+            _ => Accidental::Natural,
+        };
+        if accidental != Accidental::Natural {
+            chars.next();
+        }
+
+        let octave: String = chars.collect();
+        if octave.is_empty() {
+            return Err(ParsePitchError::MissingOctave);
+        }
+        let octave = match octave.parse::<i32>() {
+            Ok(number) if (LOWEST_OCTAVE..=HIGHEST_OCTAVE).contains(&number) => number,
+            _ => return Err(ParsePitchError::InvalidOctave(octave)),
+        };
+
+        Ok(Pitch::new(name, accidental, octave))
+    }
+}
+
+/*
+   Purpose: Lets pitches be saved in files the same way they're written, e.g. "Eb3"
+*/
+impl Serialize for Pitch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pitch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse()
+            .map_err(|err| de::Error::custom(format!("\"{}\" is not a valid note, {}", text, err)))
+    }
+}
+
+/*
+   Purpose: Names the equal-tempered note closest to the given frequency, along with how far off it is in cents
+   Notes: Example output: "F#2 -12.0 cents"
+*/
+pub fn describe_pitch(frequency: f64, reference_a4: f64) -> String {
+    let (pitch, cents) = Pitch::nearest(frequency, reference_a4);
+    format!("{} {:+.1} cents", pitch, cents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Pitch {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parsed_pitches_display_as_written() {
+        for text in ["E2", "Eb3", "D#3", "B#3", "Cb4", "C-1", "G9"] {
+            assert_eq!(parse(text).to_string(), text);
+        }
+        assert_eq!(parse("E♭3").to_string(), "Eb3");
+        assert_eq!(parse("f♯2").to_string(), "F#2");
+    }
+
+    #[test]
+    fn spellings_keep_their_octave() {
+        assert_eq!(parse("Eb3").midi(), parse("D#3").midi());
+        assert_ne!(parse("Eb3"), parse("D#3"));
+        assert_eq!(parse("B#3").midi(), parse("C4").midi());
+        assert_eq!(parse("Cb4").midi(), parse("B3").midi());
+    }
+
+    #[test]
+    fn bad_pitches_are_rejected() {
+        assert_eq!("".parse::<Pitch>(), Err(ParsePitchError::Empty));
+        assert_eq!("E".parse::<Pitch>(), Err(ParsePitchError::MissingOctave));
+        assert_eq!(
+            "E10".parse::<Pitch>(),
+            Err(ParsePitchError::InvalidOctave("10".to_string()))
+        );
+        assert_eq!(
+            "H2".parse::<Pitch>(),
+            Err(ParsePitchError::UnknownNoteName('H'))
+        );
+    }
+
+    #[test]
+    fn frequency_follows_the_reference() {
+        assert!((parse("A4").frequency(440.0) - 440.0).abs() < 1e-9);
+        assert!((parse("A4").frequency(432.0) - 432.0).abs() < 1e-9);
+        assert!((parse("E2").frequency(440.0) - 82.4069).abs() < 1e-3);
+        assert!((parse("E2").frequency(432.0) - 80.9086).abs() < 1e-3);
+        assert!((parse("A5").frequency(432.0) - 864.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn nearest_finds_the_pitch_and_cents() {
+        let (pitch, cents) = Pitch::nearest(440.0, 440.0);
+        assert_eq!(pitch, parse("A4"));
+        assert!(cents.abs() < 1e-9);
+
+        // 440 Hz is about 31.8 cents sharp of A4 when A4 is 432 Hz
+        let (pitch, cents) = Pitch::nearest(440.0, 432.0);
+        assert_eq!(pitch, parse("A4"));
+        assert!((cents - 31.77).abs() < 0.01);
+
+        let (pitch, cents) = Pitch::nearest(parse("E2").frequency(432.0) * 0.995, 432.0);
+        assert_eq!(pitch, parse("E2"));
+        assert!((cents + 8.68).abs() < 0.01);
+    }
+
+    #[test]
+    fn pitches_round_trip_through_midi() {
+        for midi in 0..128 {
+            assert_eq!(Pitch::from_midi(midi).midi(), midi);
+        }
+    }
+}
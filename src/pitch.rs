@@ -0,0 +1,792 @@
+use crate::{audio_io::AudioData, error::GuitarToolsError};
+use realfft::{num_complex::Complex, RealFftPlanner};
+use serde::{Deserialize, Serialize};
+
+pub const CHROMATIC_LOWEST_FREQUENCY: f64 = 25.0;
+pub const CHROMATIC_HIGHEST_FREQUENCY: f64 = 1400.0;
+const SUBHARMONIC_THRESHOLD: f64 = 0.2;
+const SUBHARMONIC_TOLERANCE: f64 = 0.03;
+const ZERO_PADDING_FACTOR: usize = 4;
+const YIN_THRESHOLD: f64 = 0.15;
+const YIN_MINIMUM_CONFIDENCE: f64 = 0.5;
+const AUTOCORRELATION_PEAK_RATIO: f64 = 0.9;
+// Below these confidences the other detectors report no pitch. Noise on its own scores about 0.25 for the spectrum based
+// ones and under 0.1 for autocorrelation, while even a quiet, noisy note scores 0.9 and 0.75.
+const SPECTRAL_MINIMUM_CONFIDENCE: f64 = 0.6;
+const AUTOCORRELATION_MINIMUM_CONFIDENCE: f64 = 0.3;
+const CEPSTRUM_SUBMULTIPLE_RATIO: f64 = 0.5;
+const HPS_HARMONICS: usize = 5;
+const LISTED_HARMONICS: usize = 8;
+const HARMONIC_PRESENCE: f64 = 0.05;
+
+// Sound Analysis ---------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Magnitude spectrum of a recording, along with what's needed to turn a bin index back into Hz
+   Notes: Bin k sits at k * sample_rate / fft_length Hz. The fft_length is usually longer than the recording because of zero-padding.
+*/
+pub struct Spectrum {
+    pub magnitudes: Vec<f64>,
+    pub sample_rate: u32,
+    pub fft_length: usize,
+}
+
+impl Spectrum {
+    pub fn bin_to_frequency(&self, bin: f64) -> f64 {
+        bin * self.sample_rate as f64 / self.fft_length as f64
+    }
+
+    /*
+       Purpose: Gives the closest bin to a frequency, clamped to the length of the spectrum
+    */
+    pub fn frequency_to_bin(&self, frequency: f64) -> usize {
+        let bin = (frequency * self.fft_length as f64 / self.sample_rate as f64).round();
+        (bin.max(0.0) as usize).min(self.magnitudes.len())
+    }
+}
+
+/*
+   Purpose: This takes in a vector representing a signal waveform, performs a fourier transform and outputs the spectrogram (real normalized not complex)
+   Notes: This section is based on the documents in https://docs.rs/realfft/3.3.0/realfft/ and took inspiration from the source code at https://docs.rs/audioviz/latest/src/audioviz/fft.rs.html#55-62
+          A Hann window is applied first so energy doesn't leak into the neighbouring bins, which keeps the peaks narrow enough to interpolate.
+          The signal is then zero-padded to ZERO_PADDING_FACTOR times its length (rounded up to a power of two) for finer bin spacing.
+          Window formula from https://en.wikipedia.org/wiki/Hann_function
+*/
+pub fn generate_fourier_transform(audio: AudioData) -> Result<Spectrum, GuitarToolsError> {
+    let length = audio.samples.len();
+    if length < 2 {
+        return Err(GuitarToolsError::analysis("there isn't enough audio yet"));
+    }
+    let fft_length = (length * ZERO_PADDING_FACTOR).next_power_of_two();
+
+    // We need to converet the samples into f64, windowing them along the way
+    let mut source: Vec<f64> = vec![0.0; fft_length];
+    for (i, sample) in audio.samples.iter().enumerate() {
+        let window =
+            0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / (length - 1) as f64).cos();
+        source[i] = *sample as f64 * window;
+    }
+
+    // make a planner
+    let mut real_planner = RealFftPlanner::<f64>::new();
+
+    // create a FFT
+    let r2c = real_planner.plan_fft_forward(fft_length);
+
+    // make a vector for storing the spectrum
+    let mut spectrum = r2c.make_output_vec();
+
+    // forward transform the signal
+    r2c.process(&mut source, &mut spectrum)
+        .map_err(GuitarToolsError::analysis)?;
+
+    let mut data: Vec<f64> = Vec::new();
+
+    //Normalizes complex to real
+    //Theoretically the index at our desired frequency should have the highest magnitude.
+    for i in &spectrum {
+        data.push(i.norm());
+    }
+
+    Ok(Spectrum {
+        magnitudes: data,
+        sample_rate: audio.sample_rate,
+        fft_length,
+    })
+}
+
+// Putting Note Call to Frequency output all together ---------------------------------------------------------------------------
+/*
+   Purpose: Finds the fundamental using a harmonic product spectrum, and outputs it in Hz
+   Notes: In theory, the loudest bin should be the most prominent frequency in the spectrogram
+          During testing, it seems that the second and third harmonics keep getting picked up. For example, a perfectly tuned E2 string (82 Hz) would pick up 164 Hz, 246 Hz etc.
+          This used to be handled by only searching below the target note's second harmonic, which assumed the string was already in the ballpark of the pitch.
+          Multiplying the spectrum with squashed copies of itself fixes it properly: only the frequency whose harmonics all line up stays large,
+          so the fundamental doesn't need to be the loudest partial. This matters most on low strings (D2, drop C, bass) where it usually isn't.
+*/
+pub fn find_harmonic_product_peak(spectrum: &Spectrum) -> f64 {
+    let data = &spectrum.magnitudes;
+    let start = spectrum.frequency_to_bin(CHROMATIC_LOWEST_FREQUENCY);
+    let end = spectrum
+        .frequency_to_bin(CHROMATIC_HIGHEST_FREQUENCY)
+        .min(data.len() / HPS_HARMONICS);
+
+    let product = harmonic_product_spectrum(data, HPS_HARMONICS, start, end);
+
+    // The product is only used to find the right bin, the original spectrum gives the more accurate peak shape
+    let index = find_peak_in_range(&product, start, end);
+    let index = find_peak_in_range(data, index.saturating_sub(1), index + 2);
+    spectrum.bin_to_frequency(interpolate_peak(data, index))
+}
+
+/*
+   Purpose: Downsamples the spectrum by 2, 3, ... harmonics and multiplies the copies together, bin by bin
+   Notes: Based on https://www.chciken.com/digital/signal/processing/2020/05/13/guitar-tuner.html and the overview at https://ccrma.stanford.edu/~pdelac/154/m154paper.htm
+          Bins outside of start..end are left at zero. end must be no more than data.len() / harmonics.
+*/
+pub fn harmonic_product_spectrum(
+    data: &[f64],
+    harmonics: usize,
+    start: usize,
+    end: usize,
+) -> Vec<f64> {
+    let mut product = vec![0.0; end];
+    for (bin, value) in product.iter_mut().enumerate().skip(start) {
+        *value = (1..=harmonics).map(|h| data[bin * h]).product();
+    }
+    product
+}
+
+/*
+   Purpose: Finds the fundamental frequency in the spectrogram without needing a target note, and outputs it in Hz
+   Notes: The loudest bin is found first. Because the second and third harmonics are often louder than the fundamental,
+          we then check whether there is a strong enough peak at 1/2, 1/3 or 1/4 of that bin. The lowest one that clears
+          SUBHARMONIC_THRESHOLD (relative to the loudest peak) is taken as the fundamental.
+*/
+pub fn find_fundamental(spectrum: &Spectrum) -> f64 {
+    let data = &spectrum.magnitudes;
+    let start = spectrum.frequency_to_bin(CHROMATIC_LOWEST_FREQUENCY);
+    let end = spectrum.frequency_to_bin(CHROMATIC_HIGHEST_FREQUENCY);
+    let peak = find_peak_in_range(data, start, end);
+    let peak_magnitude = data[peak];
+
+    let mut fundamental = peak;
+    for divisor in 2..=4 {
+        let candidate = peak / divisor;
+        if candidate < start {
+            break;
+        }
+        // Real strings are slightly inharmonic, so the subharmonic may be a little off from an exact division
+        let spread = (candidate as f64 * SUBHARMONIC_TOLERANCE).ceil() as usize;
+        let candidate =
+            find_peak_in_range(data, candidate - spread, (candidate + spread + 1).min(end));
+        if data[candidate] >= peak_magnitude * SUBHARMONIC_THRESHOLD {
+            fundamental = candidate;
+        }
+    }
+    spectrum.bin_to_frequency(interpolate_peak(data, fundamental))
+}
+
+/*
+   Purpose: Returns the index of the largest magnitude between start (inclusive) and end (exclusive)
+*/
+fn find_peak_in_range(data: &[f64], start: usize, end: usize) -> usize {
+    let mut index = start;
+    let mut greatest: f64 = 0.0;
+
+    for (i, x) in data.iter().enumerate().take(end).skip(start) {
+        if x.abs() > greatest {
+            greatest = x.abs();
+            index = i;
+        }
+    }
+    index
+}
+
+/*
+   Purpose: Estimates where the true peak lies between bins by fitting a parabola through the peak bin and its two neighbours
+   Notes: The fit is done on the log of the magnitudes, which is much closer to a parabola for a Hann-windowed sine.
+          Based on https://ccrma.stanford.edu/~jos/sasp/Quadratic_Interpolation_Spectral_Peaks.html
+          Outputs a fractional bin index.
+*/
+fn interpolate_peak(data: &[f64], index: usize) -> f64 {
+    if index == 0 || index + 1 >= data.len() {
+        return index as f64;
+    }
+
+    let alpha = data[index - 1].max(f64::MIN_POSITIVE).ln();
+    let beta = data[index].max(f64::MIN_POSITIVE).ln();
+    let gamma = data[index + 1].max(f64::MIN_POSITIVE).ln();
+
+    index as f64 + parabolic_offset(alpha, beta, gamma)
+}
+
+/*
+   Purpose: Gives the position of a parabola's vertex relative to the middle of three evenly spaced points
+   Notes: The result is clamped to half a step either side, since anything further means the middle point wasn't really the extreme.
+*/
+fn parabolic_offset(alpha: f64, beta: f64, gamma: f64) -> f64 {
+    let denominator = alpha - 2.0 * beta + gamma;
+    if denominator == 0.0 {
+        return 0.0;
+    }
+
+    (0.5 * (alpha - gamma) / denominator).clamp(-0.5, 0.5)
+}
+
+// Pitch Detection --------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: The result of a pitch detector
+   Notes: Confidence runs from 0 (noise) to 1 (a clean, periodic note). Each detector measures it its own way, so compare it between readings rather than between detectors.
+          Harmonics holds the frequencies of any overtones the detector found, in Hz. Time-domain detectors leave it empty.
+*/
+pub struct PitchEstimate {
+    pub frequency: f64,
+    pub confidence: f64,
+    pub harmonics: Vec<f64>,
+}
+
+/*
+   Purpose: Anything that can find the pitch of a chunk of mono audio
+   Notes: Returns None when there's no clear pitch to report, e.g. silence or noise, and an error only if the audio couldn't be analysed at all.
+          Fewer samples than enough_audio asks for (including none) is also None: the lowest note can't be heard in it yet.
+          Each detector has its own minimum confidence, so the tuner can trust whatever comes back.
+*/
+pub trait PitchDetector {
+    fn detect(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+    ) -> Result<Option<PitchEstimate>, GuitarToolsError>;
+}
+
+/*
+   Purpose: Whether there's enough audio to look for a pitch in: two periods of CHROMATIC_LOWEST_FREQUENCY
+   Notes: Every detector checks this first, so too little audio gives the same answer whichever one is picked.
+*/
+pub fn enough_audio(samples: &[f32], sample_rate: u32) -> bool {
+    samples.len() >= 2 * (sample_rate as f64 / CHROMATIC_LOWEST_FREQUENCY).ceil() as usize
+}
+
+/*
+   Purpose: Lists the available pitch detectors so they can be picked from the settings panel
+*/
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DetectorKind {
+    FftPeak,
+    HarmonicProductSpectrum,
+    Cepstrum,
+    Autocorrelation,
+    #[default]
+    Yin,
+}
+
+impl DetectorKind {
+    pub const ALL: [DetectorKind; 5] = [
+        DetectorKind::FftPeak,
+        DetectorKind::HarmonicProductSpectrum,
+        DetectorKind::Cepstrum,
+        DetectorKind::Autocorrelation,
+        DetectorKind::Yin,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DetectorKind::FftPeak => "Fourier transform peak",
+            DetectorKind::HarmonicProductSpectrum => {
+                "Fourier transform peak + harmonic product spectrum"
+            }
+            DetectorKind::Cepstrum => "Cepstrum",
+            DetectorKind::Autocorrelation => "Autocorrelation",
+            DetectorKind::Yin => "YIN (recommended)",
+        }
+    }
+
+    /*
+       Purpose: Makes the detector
+    */
+    pub fn build(&self) -> Box<dyn PitchDetector> {
+        match self {
+            DetectorKind::FftPeak => Box::new(FftPeakDetector {
+                harmonic_product: false,
+            }),
+            DetectorKind::HarmonicProductSpectrum => Box::new(FftPeakDetector {
+                harmonic_product: true,
+            }),
+            DetectorKind::Cepstrum => Box::new(CepstrumDetector),
+            DetectorKind::Autocorrelation => Box::new(AutocorrelationDetector),
+            DetectorKind::Yin => Box::new(YinDetector),
+        }
+    }
+}
+
+/*
+   Purpose: The original detector. Picks the loudest bin of the fourier transform.
+   Notes: With the harmonic product stage the peak is picked from the harmonic product spectrum instead (see find_harmonic_product_peak).
+          Without it, find_fundamental checks for a louder harmonic after picking the peak.
+          Returns None below SPECTRAL_MINIMUM_CONFIDENCE, since the loudest bin of pure noise is still somewhere.
+*/
+pub struct FftPeakDetector {
+    pub harmonic_product: bool,
+}
+
+impl PitchDetector for FftPeakDetector {
+    fn detect(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+    ) -> Result<Option<PitchEstimate>, GuitarToolsError> {
+        if !enough_audio(samples, sample_rate) {
+            return Ok(None);
+        }
+        let spectrum = generate_fourier_transform(AudioData {
+            samples: samples.to_vec(),
+            sample_rate,
+        })?;
+        let frequency = if self.harmonic_product {
+            find_harmonic_product_peak(&spectrum)
+        } else {
+            find_fundamental(&spectrum)
+        };
+        Ok(spectral_estimate(&spectrum, frequency)
+            .filter(|estimate| estimate.confidence >= SPECTRAL_MINIMUM_CONFIDENCE))
+    }
+}
+
+/*
+   Purpose: Takes the spectrum of the log spectrum. Evenly spaced harmonics turn into a single peak at the fundamental's period.
+   Notes: Based on https://en.wikipedia.org/wiki/Cepstrum and http://flothesof.github.io/cepstrum-pitch-tracking.html
+          The inverse transform is done with realfft's complex to real plan.
+          The quefrency peak only gives a rough period, the final frequency comes from the spectrum peak closest to it.
+          Returns None below SPECTRAL_MINIMUM_CONFIDENCE, which also catches a quefrency peak that didn't land on the note.
+*/
+pub struct CepstrumDetector;
+
+impl PitchDetector for CepstrumDetector {
+    fn detect(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+    ) -> Result<Option<PitchEstimate>, GuitarToolsError> {
+        if !enough_audio(samples, sample_rate) {
+            return Ok(None);
+        }
+        let spectrum = generate_fourier_transform(AudioData {
+            samples: samples.to_vec(),
+            sample_rate,
+        })?;
+
+        let mut real_planner = RealFftPlanner::<f64>::new();
+        let c2r = real_planner.plan_fft_inverse(spectrum.fft_length);
+        let mut log_spectrum: Vec<Complex<f64>> = spectrum
+            .magnitudes
+            .iter()
+            .map(|magnitude| Complex::new(magnitude.max(f64::MIN_POSITIVE).ln(), 0.0))
+            .collect();
+        let mut cepstrum = c2r.make_output_vec();
+        c2r.process(&mut log_spectrum, &mut cepstrum)
+            .map_err(GuitarToolsError::analysis)?;
+        Ok(cepstrum_estimate(&spectrum, &cepstrum, sample_rate)
+            .filter(|estimate| estimate.confidence >= SPECTRAL_MINIMUM_CONFIDENCE))
+    }
+}
+
+/*
+   Purpose: Picks the period from the cepstrum and refines it against the spectrum
+*/
+fn cepstrum_estimate(
+    spectrum: &Spectrum,
+    cepstrum: &[f64],
+    sample_rate: u32,
+) -> Option<PitchEstimate> {
+    // Quefrency is measured in samples, so a peak at q means a period of q samples
+    let rate = sample_rate as f64;
+    let start = (rate / CHROMATIC_HIGHEST_FREQUENCY).floor() as usize;
+    let end = ((rate / CHROMATIC_LOWEST_FREQUENCY).ceil() as usize).min(cepstrum.len() / 2);
+    // Unlike a spectrum the cepstrum goes negative, so this looks for the largest value rather than the largest magnitude
+    let mut index = (start..end).max_by(|a, b| cepstrum[*a].total_cmp(&cepstrum[*b]))?;
+
+    // Like autocorrelation, the cepstrum also peaks at multiples of the period. A strong enough peak at a half or a third wins.
+    let best = cepstrum[index];
+    for divisor in [3, 2] {
+        let candidate = index / divisor;
+        if candidate < start + 2 {
+            continue;
+        }
+        let candidate =
+            (candidate - 2..=candidate + 2).max_by(|a, b| cepstrum[*a].total_cmp(&cepstrum[*b]))?;
+        if cepstrum[candidate] >= best * CEPSTRUM_SUBMULTIPLE_RATIO {
+            index = candidate;
+            break;
+        }
+    }
+    if index == 0 || index + 1 >= cepstrum.len() {
+        return None;
+    }
+
+    // A quefrency of a few hundred samples is only accurate to a few cents, so the matching spectrum peak is used to finish the job
+    let period =
+        index as f64 + parabolic_offset(cepstrum[index - 1], cepstrum[index], cepstrum[index + 1]);
+    // The rough period drifts further at the lowest notes (about 4% at a bass's low B), so up to a semitone either side is searched
+    let rough = rate / period;
+    let low = spectrum.frequency_to_bin(rough * 0.94);
+    let high = spectrum.frequency_to_bin(rough * 1.06) + 1;
+    let peak = find_peak_in_range(
+        &spectrum.magnitudes,
+        low,
+        high.min(spectrum.magnitudes.len()),
+    );
+    let frequency = spectrum.bin_to_frequency(interpolate_peak(&spectrum.magnitudes, peak));
+    spectral_estimate(spectrum, frequency)
+}
+
+/*
+   Purpose: Finds the delay at which the waveform best matches itself
+   Notes: Based on https://en.wikipedia.org/wiki/Autocorrelation#Signal_processing
+          The autocorrelation also peaks at twice and three times the period, so the shortest delay within AUTOCORRELATION_PEAK_RATIO of the best is taken.
+          Confidence is the correlation at that delay relative to the correlation with no delay, and below
+          AUTOCORRELATION_MINIMUM_CONFIDENCE there's no pitch.
+          Everything it's given is used, so the longer stretch the tuner passes for low notes averages over more of their periods.
+*/
+pub struct AutocorrelationDetector;
+
+impl PitchDetector for AutocorrelationDetector {
+    fn detect(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+    ) -> Result<Option<PitchEstimate>, GuitarToolsError> {
+        autocorrelation(samples, sample_rate)
+    }
+}
+
+fn autocorrelation(
+    samples: &[f32],
+    sample_rate: u32,
+) -> Result<Option<PitchEstimate>, GuitarToolsError> {
+    let rate = sample_rate as f64;
+    let tau_min = (rate / CHROMATIC_HIGHEST_FREQUENCY).floor().max(1.0) as usize;
+    let tau_max = (rate / CHROMATIC_LOWEST_FREQUENCY).ceil() as usize;
+
+    // The window has to be at least one period of the lowest note we're looking for, and takes in the rest of the audio
+    if !enough_audio(samples, sample_rate) {
+        return Ok(None);
+    }
+    let frame: Vec<f64> = samples.iter().map(|sample| *sample as f64).collect();
+    let correlation = lagged_correlation(&frame, tau_max)?;
+    if correlation[0] <= 0.0 {
+        return Ok(None);
+    }
+
+    // Only local maxima count, otherwise the slope right after tau_min would always win
+    let is_peak = |tau: usize| {
+        correlation[tau] > correlation[tau - 1] && correlation[tau] >= correlation[tau + 1]
+    };
+    let best = (tau_min..tau_max)
+        .filter(|tau| is_peak(*tau))
+        .map(|tau| correlation[tau])
+        .fold(0.0, f64::max);
+    if best <= 0.0 {
+        return Ok(None);
+    }
+    let tau = match (tau_min..tau_max)
+        .find(|tau| is_peak(*tau) && correlation[*tau] >= best * AUTOCORRELATION_PEAK_RATIO)
+    {
+        Some(tau) => tau,
+        None => return Ok(None),
+    };
+
+    let confidence = (correlation[tau] / correlation[0]).clamp(0.0, 1.0);
+    if confidence < AUTOCORRELATION_MINIMUM_CONFIDENCE {
+        return Ok(None);
+    }
+
+    let period =
+        tau as f64 + parabolic_offset(correlation[tau - 1], correlation[tau], correlation[tau + 1]);
+    Ok(Some(PitchEstimate {
+        frequency: rate / period,
+        confidence,
+        harmonics: Vec::new(),
+    }))
+}
+
+/*
+   Purpose: Finds the fundamental frequency of the most recent audio using the YIN algorithm
+   Notes: Based on "YIN, a fundamental frequency estimator for speech and music" (de Cheveigne & Kawahara, 2002): http://audition.ens.fr/adc/pdf/2002_JASA_YIN.pdf
+          Instead of looking for the loudest frequency, YIN looks for the shortest delay at which the waveform lines up with itself.
+          A string's harmonics all repeat at the fundamental's period too, so a loud second or third harmonic doesn't fool it the way it fools the fourier transform.
+          The search covers CHROMATIC_LOWEST_FREQUENCY up to CHROMATIC_HIGHEST_FREQUENCY, so no target note is needed.
+          The integration window is all of the audio it's given apart from the longest delay, so it grows with the tuner's
+          analysis window and a low string is measured over many periods rather than one or two.
+          Returns None for silence or anything less periodic than YIN_MINIMUM_CONFIDENCE.
+*/
+pub struct YinDetector;
+
+impl PitchDetector for YinDetector {
+    fn detect(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+    ) -> Result<Option<PitchEstimate>, GuitarToolsError> {
+        yin(samples, sample_rate)
+    }
+}
+
+fn yin(samples: &[f32], sample_rate: u32) -> Result<Option<PitchEstimate>, GuitarToolsError> {
+    let rate = sample_rate as f64;
+    let tau_min = (rate / CHROMATIC_HIGHEST_FREQUENCY).floor().max(2.0) as usize;
+    let tau_max = (rate / CHROMATIC_LOWEST_FREQUENCY).ceil() as usize;
+
+    // The integration window has to be at least one period of the lowest note we're looking for
+    if !enough_audio(samples, sample_rate) {
+        return Ok(None);
+    }
+    let frame: Vec<f64> = samples.iter().map(|sample| *sample as f64).collect();
+    let window = frame.len() - tau_max;
+
+    // Step 2: The difference function, i.e. how much the waveform changes when shifted by tau samples.
+    // Expanding (a - b)^2 gives the energy of both stretches minus twice their correlation, so no delay needs its own sum.
+    let correlation = lagged_correlation(&frame, tau_max)?;
+    let mut energy = vec![0.0; frame.len() + 1];
+    for (i, sample) in frame.iter().enumerate() {
+        energy[i + 1] = energy[i] + sample * sample;
+    }
+    let mut difference = vec![0.0; tau_max + 1];
+    for (tau, value) in difference.iter_mut().enumerate().skip(1) {
+        let delayed_energy = energy[tau + window] - energy[tau];
+        *value = (energy[window] + delayed_energy - 2.0 * correlation[tau]).max(0.0);
+    }
+
+    // Step 3: Cumulative mean normalized difference, which stops tiny delays from always winning
+    let mut normalized = vec![1.0; tau_max + 1];
+    let mut running_sum = 0.0;
+    for tau in 1..=tau_max {
+        running_sum += difference[tau];
+        if running_sum > 0.0 {
+            normalized[tau] = difference[tau] * tau as f64 / running_sum;
+        }
+    }
+    if running_sum == 0.0 {
+        return Ok(None);
+    }
+
+    // Step 4: Absolute threshold. Take the first dip below YIN_THRESHOLD and follow it down to its minimum.
+    // If nothing dips that low, fall back to the deepest dip overall (which will have a low confidence).
+    let mut best = None;
+    let mut tau = tau_min;
+    while tau < tau_max {
+        if normalized[tau] < YIN_THRESHOLD {
+            while tau + 1 < tau_max && normalized[tau + 1] < normalized[tau] {
+                tau += 1;
+            }
+            best = Some(tau);
+            break;
+        }
+        tau += 1;
+    }
+    let tau = best.unwrap_or_else(|| {
+        (tau_min..tau_max)
+            .min_by(|a, b| normalized[*a].total_cmp(&normalized[*b]))
+            .unwrap_or(tau_min)
+    });
+
+    let confidence = (1.0 - normalized[tau]).clamp(0.0, 1.0);
+    if confidence < YIN_MINIMUM_CONFIDENCE {
+        return Ok(None);
+    }
+
+    // Step 5: Parabolic interpolation for a period that falls between samples
+    let period =
+        tau as f64 + parabolic_offset(normalized[tau - 1], normalized[tau], normalized[tau + 1]);
+
+    Ok(Some(PitchEstimate {
+        frequency: rate / period,
+        confidence,
+        harmonics: Vec::new(),
+    }))
+}
+
+/*
+   Purpose: Correlates the start of the audio with itself delayed by 0 to tau_max samples, over all but the last tau_max samples
+   Notes: Done with the fourier transform rather than a sum for every delay, so it stays quick over the second or more of audio
+          the tuner gives low strings: https://en.wikipedia.org/wiki/Autocorrelation#Efficient_computation
+          The transform is long enough that the delayed copy never wraps round onto the start.
+*/
+fn lagged_correlation(frame: &[f64], tau_max: usize) -> Result<Vec<f64>, GuitarToolsError> {
+    let window = frame.len() - tau_max;
+    let fft_length = frame.len().next_power_of_two();
+
+    let mut real_planner = RealFftPlanner::<f64>::new();
+    let r2c = real_planner.plan_fft_forward(fft_length);
+    let c2r = real_planner.plan_fft_inverse(fft_length);
+
+    let mut start = vec![0.0; fft_length];
+    start[..window].copy_from_slice(&frame[..window]);
+    let mut whole = vec![0.0; fft_length];
+    whole[..frame.len()].copy_from_slice(frame);
+    let mut start_spectrum = r2c.make_output_vec();
+    let mut whole_spectrum = r2c.make_output_vec();
+    r2c.process(&mut start, &mut start_spectrum)
+        .map_err(GuitarToolsError::analysis)?;
+    r2c.process(&mut whole, &mut whole_spectrum)
+        .map_err(GuitarToolsError::analysis)?;
+
+    let mut product: Vec<Complex<f64>> = start_spectrum
+        .iter()
+        .zip(&whole_spectrum)
+        .map(|(start, whole)| start.conj() * whole)
+        .collect();
+    // The inverse transform rejects any rounding error left in the imaginary parts that should be zero
+    if let Some(first) = product.first_mut() {
+        first.im = 0.0;
+    }
+    if let Some(last) = product.last_mut() {
+        last.im = 0.0;
+    }
+    let mut correlation = c2r.make_output_vec();
+    c2r.process(&mut product, &mut correlation)
+        .map_err(GuitarToolsError::analysis)?;
+
+    // realfft leaves the result scaled up by the transform length
+    Ok(correlation[..=tau_max]
+        .iter()
+        .map(|value| value / fft_length as f64)
+        .collect())
+}
+
+/*
+   Purpose: Builds the estimate for the spectrum based detectors, filling in the harmonics and a confidence
+   Notes: Confidence is the share of the spectrum's energy that sits on the fundamental and its harmonics.
+          Returns None if the spectrum is silent.
+*/
+fn spectral_estimate(spectrum: &Spectrum, frequency: f64) -> Option<PitchEstimate> {
+    let data = &spectrum.magnitudes;
+    let start = spectrum.frequency_to_bin(CHROMATIC_LOWEST_FREQUENCY);
+    let end = spectrum.frequency_to_bin(frequency * (LISTED_HARMONICS as f64 + 0.5));
+
+    if !frequency.is_finite() || end <= start {
+        return None;
+    }
+
+    let total: f64 = data[start..end].iter().map(|x| x * x).sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    // Bins within half a semitone either side of each harmonic count towards it
+    let mut harmonic_energy = 0.0;
+    let mut harmonics = Vec::new();
+    let loudest = data[start..end].iter().cloned().fold(0.0, f64::max);
+    for h in 1..=LISTED_HARMONICS {
+        let low = spectrum
+            .frequency_to_bin(frequency * h as f64 * 0.97)
+            .max(start);
+        let high = spectrum
+            .frequency_to_bin(frequency * h as f64 * 1.03)
+            .min(end);
+        if low >= high {
+            continue;
+        }
+        harmonic_energy += data[low..high].iter().map(|x| x * x).sum::<f64>();
+
+        let index = find_peak_in_range(data, low, high);
+        if h > 1 && data[index] >= loudest * HARMONIC_PRESENCE {
+            harmonics.push(spectrum.bin_to_frequency(interpolate_peak(data, index)));
+        }
+    }
+
+    Some(PitchEstimate {
+        frequency,
+        confidence: (harmonic_energy / total).clamp(0.0, 1.0),
+        harmonics,
+    })
+}
+
+/*
+   Purpose: Lists the overtones of the given fundamental that stand out in the spectrum
+   Notes: Lets the time-domain detectors' results be shown with harmonics too, e.g. in the analysis view.
+*/
+pub fn find_harmonics(spectrum: &Spectrum, frequency: f64) -> Vec<f64> {
+    spectral_estimate(spectrum, frequency).map_or_else(Vec::new, |estimate| estimate.harmonics)
+}
+
+/*
+   Purpose: Gives how far a frequency is from a reference, in cents (hundredths of a semitone)
+   Notes: Positive means the frequency is sharp of the reference, negative means flat.
+*/
+pub fn cents_between(frequency: f64, reference: f64) -> f64 {
+    1200.0 * (frequency / reference).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 44100;
+
+    // A low E with its fundamental much quieter than the next few harmonics, as a guitar's often is
+    fn weak_fundamental(frequency: f64, seconds: f64) -> Vec<f32> {
+        const AMPLITUDES: [f64; 5] = [0.25, 1.0, 0.8, 0.5, 0.3];
+        (0..(seconds * SAMPLE_RATE as f64) as usize)
+            .map(|i| {
+                let time = i as f64 / SAMPLE_RATE as f64;
+                let sample: f64 = AMPLITUDES
+                    .iter()
+                    .enumerate()
+                    .map(|(h, amplitude)| {
+                        amplitude
+                            * (2.0 * std::f64::consts::PI * frequency * (h + 1) as f64 * time).sin()
+                    })
+                    .sum();
+                (0.2 * sample) as f32
+            })
+            .collect()
+    }
+
+    // White noise from a fixed seed, so the test always hears the same thing
+    fn noise(length: usize) -> Vec<f32> {
+        let mut state: u32 = 0x1234_5678;
+        (0..length)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state as f64 / u32::MAX as f64 * 2.0 - 1.0) as f32 * 0.5
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detectors_find_a_weak_fundamental() {
+        let frequency = 82.4069;
+        let samples = weak_fundamental(frequency, 0.4);
+        for kind in DetectorKind::ALL {
+            let estimate = kind
+                .build()
+                .detect(&samples, SAMPLE_RATE)
+                .unwrap()
+                .unwrap_or_else(|| panic!("{} found nothing", kind.label()));
+            let cents = cents_between(estimate.frequency, frequency);
+            assert!(
+                cents.abs() < 5.0,
+                "{} was {:+.1} cents off",
+                kind.label(),
+                cents
+            );
+        }
+    }
+
+    #[test]
+    fn detectors_hear_nothing_in_silence_or_noise() {
+        let silence = vec![0.0; SAMPLE_RATE as usize / 2];
+        let noise = noise(SAMPLE_RATE as usize / 2);
+        for kind in DetectorKind::ALL {
+            let detector = kind.build();
+            assert!(
+                detector.detect(&silence, SAMPLE_RATE).unwrap().is_none(),
+                "{} heard silence",
+                kind.label()
+            );
+            assert!(
+                detector.detect(&noise, SAMPLE_RATE).unwrap().is_none(),
+                "{} heard noise",
+                kind.label()
+            );
+        }
+    }
+
+    #[test]
+    fn detectors_wait_for_enough_audio() {
+        let samples = weak_fundamental(82.4069, 0.4);
+        for kind in DetectorKind::ALL {
+            let detector = kind.build();
+            for length in [0, 10, 2 * 1764 - 1] {
+                assert!(
+                    matches!(detector.detect(&samples[..length], SAMPLE_RATE), Ok(None)),
+                    "{} with {} samples",
+                    kind.label(),
+                    length
+                );
+            }
+        }
+    }
+}
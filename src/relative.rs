@@ -0,0 +1,339 @@
+use crate::{
+    notes::{Pitch, STANDARD_A4},
+    pitch::cents_between,
+    tuning::Tuning,
+};
+use std::fmt;
+
+// The natural harmonics that are easy to play, as the harmonic's number and the fret it's touched over
+const HARMONICS: [(u32, u32); 3] = [(2, 12), (3, 7), (4, 5)];
+
+// RELATIVE TUNING --------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: The two classic ways of tuning one string against another
+   Notes: Fretted is the "5th fret method": the lower string is fretted so it plays the next string's open note
+          (the 4th fret for G to B on a guitar). Harmonics touches both strings lightly over frets such as the 5th and 7th,
+          which ring longer and higher, so beats between them are easier to hear.
+*/
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RelativeMethod {
+    #[default]
+    Fretted,
+    Harmonics,
+}
+
+impl RelativeMethod {
+    pub const ALL: [RelativeMethod; 2] = [RelativeMethod::Fretted, RelativeMethod::Harmonics];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RelativeMethod::Fretted => "5th fret",
+            RelativeMethod::Harmonics => "Harmonics",
+        }
+    }
+}
+
+/*
+   Purpose: How a note is played on a string
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Technique {
+    Open,
+    Fretted(u32),
+    // The harmonic's number, e.g. 3 for the one over the 7th fret, which sounds an octave and a fifth above the open string
+    Harmonic { harmonic: u32, fret: u32 },
+}
+
+/*
+   Purpose: A note played on one string of a tuning
+   Notes: string is its index in the tuning's strings, in the order they're listed.
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StringNote {
+    pub string: usize,
+    pub open: Pitch,
+    pub technique: Technique,
+}
+
+impl StringNote {
+    fn new(string: usize, open: Pitch, technique: Technique) -> Self {
+        Self {
+            string,
+            open,
+            technique,
+        }
+    }
+
+    /*
+       Purpose: The frequency the note sounds at once its string is in tune
+       Notes: A harmonic is an exact multiple of the open string, so unlike a fretted note it isn't quite equal tempered.
+    */
+    pub fn frequency(&self, reference_a4: f64) -> f64 {
+        let open = self.open.frequency(reference_a4);
+        match self.technique {
+            Technique::Open => open,
+            Technique::Fretted(fret) => open * 2f64.powf(fret as f64 / 12.0),
+            Technique::Harmonic { harmonic, .. } => open * harmonic as f64,
+        }
+    }
+
+    /*
+       Purpose: The nearest note to what it sounds, e.g. B3 for the harmonic over the 7th fret of a low E string
+    */
+    pub fn pitch(&self) -> Pitch {
+        let semitones = match self.technique {
+            Technique::Open => 0,
+            Technique::Fretted(fret) => fret as i32,
+            Technique::Harmonic { harmonic, .. } => harmonic_semitones(harmonic),
+        };
+        Pitch::from_midi(self.open.midi() + semitones)
+    }
+}
+
+impl fmt::Display for StringNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.technique {
+            Technique::Open => write!(f, "the open {} string", self.open),
+            Technique::Fretted(fret) => {
+                write!(f, "the {} string at the {} fret", self.open, ordinal(fret))
+            }
+            Technique::Harmonic { fret, .. } => write!(
+                f,
+                "the harmonic over the {} fret of the {} string",
+                ordinal(fret),
+                self.open
+            ),
+        }
+    }
+}
+
+/*
+   Purpose: One step of tuning by ear string to string: a note on a string that's already in tune, and the note on the next
+            string that should match it
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RelativeStep {
+    pub reference: StringNote,
+    pub target: StringNote,
+}
+
+/*
+   Purpose: How the target note compares to the reference note as they were played
+   Notes: cents is how far the target string is from where it should be, so 0 is in tune even for harmonics.
+          beat_frequency is how many times a second the two notes would beat if they rang together.
+*/
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RelativeReading {
+    pub cents: f64,
+    pub beat_frequency: f64,
+}
+
+impl RelativeStep {
+    /*
+       Purpose: How far apart in cents the two notes are once both strings are in tune
+       Notes: 0 for a fretted note. Harmonics are pure intervals, so they sit about 2 cents apart for a fourth or a fifth:
+              an equal tempered guitar's harmonics beat slowly even when it's in tune.
+    */
+    pub fn expected_cents(&self) -> f64 {
+        cents_between(
+            self.target.frequency(STANDARD_A4),
+            self.reference.frequency(STANDARD_A4),
+        )
+    }
+
+    pub fn compare(&self, reference_frequency: f64, target_frequency: f64) -> RelativeReading {
+        RelativeReading {
+            cents: cents_between(target_frequency, reference_frequency) - self.expected_cents(),
+            beat_frequency: (target_frequency - reference_frequency).abs(),
+        }
+    }
+}
+
+/*
+   Purpose: Works out the steps for tuning a whole tuning string to string with the given method
+   Notes: Strings are taken from lowest to highest pitch, so re-entrant tunings work too. Each one is tuned against the one
+          below it, which means the lowest string has to be tuned some other way first (by ear or with the tuner).
+          For harmonics, a pair of strings a fourth, a fifth, an octave or a unison apart shares a harmonic. Anything else, like
+          the major third from G to B, is checked against a harmonic on a lower string instead (the 7th fret of the low E
+          matches the open B). If no string has one, that step falls back to the fretted method.
+*/
+pub fn relative_steps(tuning: &Tuning, method: RelativeMethod) -> Vec<RelativeStep> {
+    let mut strings: Vec<(usize, Pitch)> = tuning.strings.iter().copied().enumerate().collect();
+    strings.sort_by_key(|(_, pitch)| pitch.midi());
+
+    (1..strings.len())
+        .map(|i| {
+            let harmonics = match method {
+                RelativeMethod::Harmonics => harmonic_step(&strings[..i], strings[i]),
+                RelativeMethod::Fretted => None,
+            };
+            harmonics.unwrap_or_else(|| fretted_step(strings[i - 1], strings[i]))
+        })
+        .collect()
+}
+
+fn fretted_step(reference: (usize, Pitch), target: (usize, Pitch)) -> RelativeStep {
+    let fret = (target.1.midi() - reference.1.midi()) as u32;
+    let technique = match fret {
+        0 => Technique::Open,
+        fret => Technique::Fretted(fret),
+    };
+    RelativeStep {
+        reference: StringNote::new(reference.0, reference.1, technique),
+        target: StringNote::new(target.0, target.1, Technique::Open),
+    }
+}
+
+/*
+   Purpose: Finds a harmonic on one of the tuned strings that matches a harmonic or the open note of the target string
+   Notes: The nearest string is tried first, since its harmonics are the closest in tone.
+*/
+fn harmonic_step(tuned: &[(usize, Pitch)], target: (usize, Pitch)) -> Option<RelativeStep> {
+    let target_techniques = HARMONICS
+        .iter()
+        .map(|&(harmonic, fret)| Technique::Harmonic { harmonic, fret })
+        .chain([Technique::Open]);
+    tuned.iter().rev().find_map(|&(string, open)| {
+        HARMONICS.iter().find_map(|&(harmonic, fret)| {
+            let reference = StringNote::new(string, open, Technique::Harmonic { harmonic, fret });
+            target_techniques
+                .clone()
+                .map(|technique| StringNote::new(target.0, target.1, technique))
+                .find(|note| note.pitch() == reference.pitch())
+                .map(|target| RelativeStep { reference, target })
+        })
+    })
+}
+
+/*
+   Purpose: How many semitones above the open string a harmonic sounds, to the nearest semitone
+*/
+fn harmonic_semitones(harmonic: u32) -> i32 {
+    (12.0 * (harmonic as f64).log2()).round() as i32
+}
+
+fn ordinal(number: u32) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", number, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tuning::built_in_tunings;
+
+    fn pitch(text: &str) -> Pitch {
+        text.parse().unwrap()
+    }
+
+    fn standard_guitar() -> Tuning {
+        built_in_tunings().remove(0)
+    }
+
+    #[test]
+    fn fretted_steps_match_the_next_open_string() {
+        let steps = relative_steps(&standard_guitar(), RelativeMethod::Fretted);
+        let frets: Vec<Technique> = steps.iter().map(|step| step.reference.technique).collect();
+        assert_eq!(
+            frets,
+            [5, 5, 5, 4, 5].map(Technique::Fretted),
+            "G to B is a major third, so it's the 4th fret"
+        );
+
+        let g_to_b = steps[3];
+        assert_eq!(g_to_b.reference.open, pitch("G3"));
+        assert_eq!(g_to_b.target.open, pitch("B3"));
+        assert_eq!(g_to_b.target.technique, Technique::Open);
+        assert_eq!(g_to_b.reference.pitch(), pitch("B3"));
+        assert!(g_to_b.expected_cents().abs() < 1e-9);
+    }
+
+    #[test]
+    fn harmonics_reach_past_the_major_third() {
+        let steps = relative_steps(&standard_guitar(), RelativeMethod::Harmonics);
+        assert_eq!(steps.len(), 5);
+
+        // The 5th fret harmonic of the low E against the 7th fret harmonic of the A
+        assert_eq!(
+            steps[0].reference.technique,
+            Technique::Harmonic {
+                harmonic: 4,
+                fret: 5
+            }
+        );
+        assert_eq!(
+            steps[0].target.technique,
+            Technique::Harmonic {
+                harmonic: 3,
+                fret: 7
+            }
+        );
+
+        // Nothing on the G string matches the B, so the low E's 7th fret harmonic is used instead
+        let b = steps[3];
+        assert_eq!(b.reference.open, pitch("E2"));
+        assert_eq!(
+            b.reference.technique,
+            Technique::Harmonic {
+                harmonic: 3,
+                fret: 7
+            }
+        );
+        assert_eq!(b.target.open, pitch("B3"));
+        assert_eq!(b.target.technique, Technique::Open);
+        assert_eq!(
+            b.reference.to_string(),
+            "the harmonic over the 7th fret of the E2 string"
+        );
+        // A pure fifth is about 2 cents wider than an equal tempered one
+        assert!((b.expected_cents() + 1.955).abs() < 0.01);
+    }
+
+    #[test]
+    fn compare_allows_for_pure_harmonics() {
+        let step = relative_steps(&standard_guitar(), RelativeMethod::Harmonics)[3];
+        let reference = step.reference.frequency(STANDARD_A4);
+        let in_tune = step.compare(reference, pitch("B3").frequency(STANDARD_A4));
+        assert!(in_tune.cents.abs() < 1e-9);
+        assert!((in_tune.beat_frequency - 0.28).abs() < 0.01);
+
+        let sharp = step.compare(
+            reference,
+            pitch("B3").frequency(STANDARD_A4) * 2f64.powf(10.0 / 1200.0),
+        );
+        assert!((sharp.cents - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn re_entrant_tunings_go_by_pitch() {
+        let ukulele = built_in_tunings()
+            .into_iter()
+            .find(|tuning| tuning.name == "Standard Tuning" && tuning.is_reentrant())
+            .unwrap();
+        let steps = relative_steps(&ukulele, RelativeMethod::Fretted);
+        let order: Vec<String> = steps
+            .iter()
+            .map(|step| step.target.open.to_string())
+            .collect();
+        assert_eq!(order, ["E4", "G4", "A4"]);
+        assert_eq!(steps[0].reference.open, pitch("C4"));
+    }
+
+    #[test]
+    fn ordinals() {
+        let ordinals: Vec<String> = [1, 2, 3, 4, 11, 12, 13, 21, 22]
+            .into_iter()
+            .map(ordinal)
+            .collect();
+        assert_eq!(
+            ordinals,
+            ["1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd"]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{
+    audio_io::InputSettings,
+    notes::STANDARD_A4,
+    pitch::DetectorKind,
+    synth::{SequenceSettings, ToneSettings, DEFAULT_VOLUME_DB, GAP_RANGE, VOLUME_RANGE_DB},
+    tuning::Instrument,
+};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, ops::RangeInclusive, path::PathBuf};
+
+pub const REFERENCE_A4_RANGE: RangeInclusive<f64> = 415.0..=466.0;
+const SETTINGS_FILE: &str = "settings.toml";
+
+// SETTINGS ---------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Everything the app remembers between sessions
+   Notes: Stored as TOML in the user's config directory, e.g. ~/.config/guitar_tools/settings.toml on Linux.
+          Missing fields fall back to their defaults, so older settings files keep working as new settings are added.
+*/
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Concert pitch in Hz. 440 is standard, 415 is baroque pitch and some orchestras or pianos sit at 442.
+    pub reference_a4: f64,
+    // Which instrument's tunings are listed on the tuning pages
+    pub instrument: Instrument,
+    // Which pitch detector the tuner uses
+    pub detector: DetectorKind,
+    // Whether the strobe is shown under the needle on the "Tune by recording" page
+    pub show_strobe: bool,
+    // Where the tuner records from
+    pub input: InputSettings,
+    // Where reference tones are played. None is the system default.
+    pub output_device: Option<String>,
+    // What the reference tones sound like, and how loud they are in dBFS
+    pub tone: ToneSettings,
+    pub volume_db: f64,
+    // How "Play all" plays a whole tuning on the "Tune by ear" page
+    pub sequence: SequenceSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            reference_a4: STANDARD_A4,
+            instrument: Instrument::default(),
+            detector: DetectorKind::default(),
+            show_strobe: false,
+            input: InputSettings::default(),
+            output_device: None,
+            tone: ToneSettings::default(),
+            volume_db: DEFAULT_VOLUME_DB,
+            sequence: SequenceSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /*
+       Purpose: Reads the settings file, falling back to the defaults if there isn't one yet
+       Notes: A broken file shouldn't stop the app from starting, so any problem is printed and the defaults are used.
+    */
+    pub fn load() -> Self {
+        let path = match settings_path() {
+            Some(path) if path.exists() => path,
+            _ => return Self::default(),
+        };
+
+        let mut settings: Settings = match fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| toml::from_str(&text).map_err(|err| err.to_string()))
+        {
+            Ok(settings) => settings,
+            Err(err) => {
+                eprintln!("could not read {}: {}", path.display(), err);
+                return Self::default();
+            }
+        };
+
+        settings.reference_a4 = settings
+            .reference_a4
+            .clamp(*REFERENCE_A4_RANGE.start(), *REFERENCE_A4_RANGE.end());
+        settings.volume_db = settings
+            .volume_db
+            .clamp(*VOLUME_RANGE_DB.start(), *VOLUME_RANGE_DB.end());
+        settings.sequence.gap = settings
+            .sequence
+            .gap
+            .clamp(*GAP_RANGE.start(), *GAP_RANGE.end());
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = settings_path().ok_or("could not find a config directory")?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/*
+   Purpose: The folder Guitar Tools keeps its files in
+   Notes: Uses the platform's config directory from https://docs.rs/dirs/latest/dirs/fn.config_dir.html
+*/
+pub fn config_directory() -> Option<PathBuf> {
+    dirs::config_dir().map(|directory| directory.join("guitar_tools"))
+}
+
+fn settings_path() -> Option<PathBuf> {
+    config_directory().map(|directory| directory.join(SETTINGS_FILE))
+}
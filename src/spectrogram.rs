@@ -0,0 +1,210 @@
+use realfft::{RealFftPlanner, RealToComplex};
+use std::{collections::VecDeque, f64::consts::PI, sync::Arc};
+
+pub const SPECTROGRAM_FFT_SIZES: [usize; 5] = [1024, 2048, 4096, 8192, 16384];
+pub const SPECTROGRAM_LOWEST_FREQUENCY: f64 = 25.0;
+pub const SPECTROGRAM_HIGHEST_FREQUENCY: f64 = 4000.0;
+pub const SPECTROGRAM_ROWS: usize = 256;
+// Anything quieter than this is stored as this, it's far below what a microphone can pick up anyway
+pub const SPECTROGRAM_FLOOR_DB: f32 = -160.0;
+const HISTORY_SECONDS: f64 = 10.0;
+// Keeps the image the gui builds from the columns within what graphics cards accept, however small the hop
+const MAX_COLUMNS: usize = 4096;
+
+// WINDOW FUNCTIONS -------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: The window applied to each frame before its fourier transform
+   Notes: Formulas from https://en.wikipedia.org/wiki/Window_function
+          Rectangular has the sharpest peaks but smears energy everywhere, Blackman the least smearing but the widest peaks.
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WindowFunction {
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl WindowFunction {
+    pub const ALL: [WindowFunction; 4] = [
+        WindowFunction::Rectangular,
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::Blackman,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WindowFunction::Rectangular => "Rectangular",
+            WindowFunction::Hann => "Hann",
+            WindowFunction::Hamming => "Hamming",
+            WindowFunction::Blackman => "Blackman",
+        }
+    }
+
+    pub fn coefficients(&self, length: usize) -> Vec<f64> {
+        let last = (length.max(2) - 1) as f64;
+        (0..length)
+            .map(|i| {
+                let phase = 2.0 * PI * i as f64 / last;
+                match self {
+                    WindowFunction::Rectangular => 1.0,
+                    WindowFunction::Hann => 0.5 - 0.5 * phase.cos(),
+                    WindowFunction::Hamming => 0.54 - 0.46 * phase.cos(),
+                    WindowFunction::Blackman => {
+                        0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos()
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+// SPECTROGRAM ------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: How the spectrogram slices up the audio
+   Notes: fft_size is the length of each frame in samples, hop is how far apart the frames start.
+          A bigger fft_size resolves pitch more finely but smears it over a longer time, and a smaller hop scrolls more smoothly.
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SpectrogramSettings {
+    pub fft_size: usize,
+    pub hop: usize,
+    pub window: WindowFunction,
+}
+
+impl Default for SpectrogramSettings {
+    fn default() -> Self {
+        Self {
+            fft_size: 4096,
+            hop: 1024,
+            window: WindowFunction::Hann,
+        }
+    }
+}
+
+/*
+   Purpose: A scrolling history of short-time spectra, one column per hop, for the last HISTORY_SECONDS (or MAX_COLUMNS columns)
+   Notes: Based on https://en.wikipedia.org/wiki/Short-time_Fourier_transform
+          Each column is SPECTROGRAM_ROWS values in dBFS, on log-spaced bands from SPECTROGRAM_LOWEST_FREQUENCY up to
+          SPECTROGRAM_HIGHEST_FREQUENCY, lowest first. Using fixed bands keeps the columns the same size whatever the fft_size.
+          A full-scale sine reads 0 dB.
+*/
+pub struct Spectrogram {
+    settings: SpectrogramSettings,
+    hop: usize,
+    sample_rate: u32,
+    fft: Arc<dyn RealToComplex<f64>>,
+    window: Vec<f64>,
+    // Bin range [start, end) that each row takes its loudest value from
+    rows: Vec<(usize, usize)>,
+    pending: Vec<f32>,
+    columns: VecDeque<Vec<f32>>,
+    max_columns: usize,
+}
+
+impl Spectrogram {
+    pub fn new(settings: SpectrogramSettings, sample_rate: u32) -> Self {
+        // Frames can't start further apart than they are long, or some audio would never be looked at
+        let hop = settings.hop.clamp(1, settings.fft_size);
+        let fft = RealFftPlanner::<f64>::new().plan_fft_forward(settings.fft_size);
+        let bins = settings.fft_size / 2 + 1;
+
+        let to_bin = |row: usize| {
+            let bin = Self::row_frequency(row) * settings.fft_size as f64 / sample_rate as f64;
+            (bin.round() as usize).min(bins - 1)
+        };
+        let rows = (0..SPECTROGRAM_ROWS)
+            .map(|row| {
+                let start = to_bin(row);
+                (start, to_bin(row + 1).max(start + 1))
+            })
+            .collect();
+
+        Self {
+            settings,
+            hop,
+            sample_rate,
+            fft,
+            window: settings.window.coefficients(settings.fft_size),
+            rows,
+            pending: Vec::with_capacity(settings.fft_size * 2),
+            columns: VecDeque::new(),
+            max_columns: ((HISTORY_SECONDS * sample_rate as f64 / hop as f64).ceil() as usize)
+                .min(MAX_COLUMNS),
+        }
+    }
+
+    pub fn settings(&self) -> SpectrogramSettings {
+        self.settings
+    }
+
+    /*
+       Purpose: Adds newly recorded samples, turning every full frame into a column
+       Notes: Leftover samples are kept for the next call, so it doesn't matter how the audio is split up.
+    */
+    pub fn push(&mut self, samples: &[f32]) {
+        self.pending.extend_from_slice(samples);
+        let mut used = 0;
+        while self.pending.len() - used >= self.settings.fft_size {
+            let column = self.column(used);
+            self.columns.push_back(column);
+            used += self.hop;
+        }
+        self.pending.drain(..used);
+
+        while self.columns.len() > self.max_columns {
+            self.columns.pop_front();
+        }
+    }
+
+    fn column(&self, offset: usize) -> Vec<f32> {
+        let frame = &self.pending[offset..offset + self.settings.fft_size];
+        let mut source: Vec<f64> = frame
+            .iter()
+            .zip(&self.window)
+            .map(|(sample, window)| *sample as f64 * window)
+            .collect();
+        let mut spectrum = self.fft.make_output_vec();
+        if self.fft.process(&mut source, &mut spectrum).is_err() {
+            return vec![SPECTROGRAM_FLOOR_DB; SPECTROGRAM_ROWS];
+        }
+
+        // A sine of amplitude 1 peaks at half the window's sum, so this scales it to 0 dB
+        let full_scale = self.window.iter().sum::<f64>() / 2.0;
+        self.rows
+            .iter()
+            .map(|(start, end)| {
+                let magnitude = spectrum[*start..*end]
+                    .iter()
+                    .map(|bin| bin.norm())
+                    .fold(0.0, f64::max);
+                ((20.0 * (magnitude / full_scale).log10()) as f32).max(SPECTROGRAM_FLOOR_DB)
+            })
+            .collect()
+    }
+
+    /*
+       Purpose: The columns in the order they were recorded, oldest first
+    */
+    pub fn columns(&self) -> &VecDeque<Vec<f32>> {
+        &self.columns
+    }
+
+    pub fn max_columns(&self) -> usize {
+        self.max_columns
+    }
+
+    pub fn seconds_per_column(&self) -> f64 {
+        self.hop as f64 / self.sample_rate as f64
+    }
+
+    /*
+       Purpose: The frequency at the bottom edge of a row, in Hz
+    */
+    pub fn row_frequency(row: usize) -> f64 {
+        let low = SPECTROGRAM_LOWEST_FREQUENCY.log10();
+        let high = SPECTROGRAM_HIGHEST_FREQUENCY.log10();
+        10f64.powf(low + (high - low) * row as f64 / SPECTROGRAM_ROWS as f64)
+    }
+}
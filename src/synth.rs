@@ -0,0 +1,975 @@
+use crate::{
+    audio_io::{default_output_device_name, open_output_stream},
+    error::GuitarToolsError,
+    notes::Pitch,
+};
+use rodio::{OutputStream, Source};
+use serde::{Deserialize, Serialize};
+use std::{
+    f64::consts::PI,
+    ops::RangeInclusive,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    time::Duration,
+};
+
+// The volume slider's range in dBFS, i.e. decibels below a full scale sine
+pub const VOLUME_RANGE_DB: RangeInclusive<f64> = -60.0..=0.0;
+pub const DEFAULT_VOLUME_DB: f64 = -18.0;
+// Nothing leaves the player louder than this, in dBFS. Just under full scale leaves room for the device's own resampling.
+const LIMITER_CEILING_DB: f64 = -1.0;
+// How long the limiter takes to let go after a peak, and how long a volume change takes to fade in, in seconds
+const LIMITER_RECOVERY_SECONDS: f64 = 0.1;
+const VOLUME_SMOOTHING_SECONDS: f64 = 0.02;
+const TONE_SECONDS: f64 = 1.0;
+// The oscillators get a soft attack that settles to a sustain level. The plucked string decays by itself, so its envelope only
+// takes the edges off.
+const OSCILLATOR_ENVELOPE: Envelope = Envelope {
+    attack: 0.01,
+    decay: 0.1,
+    sustain: 0.8,
+    release: 0.08,
+};
+const PLUCKED_ENVELOPE: Envelope = Envelope {
+    attack: 0.002,
+    decay: 0.0,
+    sustain: 1.0,
+    release: 0.08,
+};
+pub const BRIGHTNESS_RANGE: RangeInclusive<f64> = 0.0..=1.0;
+// How long a plucked note takes to die away by 60 dB, in seconds
+pub const DECAY_RANGE: RangeInclusive<f64> = 0.5..=8.0;
+// Relative loudness of each harmonic in the harmonic-rich timbre, starting with the fundamental. Roughly a plucked string's.
+const HARMONIC_AMPLITUDES: [f64; 8] = [1.0, 0.7, 0.5, 0.35, 0.25, 0.18, 0.12, 0.08];
+// A plucked drone is plucked again this often, in seconds, and dies away slowly enough to only drop by about 6 dB in between
+const DRONE_PLUCK_INTERVAL: f64 = 2.0;
+const DRONE_DECAY: f64 = DRONE_PLUCK_INTERVAL * 10.0;
+// The time between one string and the next when a tuning is played as an arpeggio, in seconds
+pub const GAP_RANGE: RangeInclusive<f64> = 0.2..=2.0;
+// How far apart the strings start in a strum, in seconds. About how long a pick takes to cross a string.
+const STRUM_STAGGER: f64 = 0.03;
+
+// TIMBRES ----------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: What the reference tones sound like
+   Notes: A pure sine is the easiest to hear beats against but small speakers barely play a low E's 82 Hz fundamental.
+          The harmonic-rich and plucked timbres put most of their sound in the harmonics, so the pitch still comes through.
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Timbre {
+    Sine,
+    Harmonics,
+    Plucked,
+}
+
+impl Timbre {
+    pub const ALL: [Timbre; 3] = [Timbre::Sine, Timbre::Harmonics, Timbre::Plucked];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Timbre::Sine => "Sine",
+            Timbre::Harmonics => "Harmonic-rich",
+            Timbre::Plucked => "Plucked string",
+        }
+    }
+}
+
+/*
+   Purpose: The timbre of the reference tones and how the plucked string sounds
+   Notes: brightness (0 to 1) is how long the plucked string's upper harmonics ring, decay is how long the whole note lasts.
+          Both only apply to the plucked timbre, and a plucked drone keeps its own slow decay so it doesn't fade between plucks.
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToneSettings {
+    pub timbre: Timbre,
+    pub brightness: f64,
+    pub decay: f64,
+}
+
+impl Default for ToneSettings {
+    fn default() -> Self {
+        Self {
+            timbre: Timbre::Sine,
+            brightness: 0.5,
+            decay: 3.0,
+        }
+    }
+}
+
+// SEQUENCES --------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Whether "Play all" plays a tuning's strings one after another or strums them together as a chord
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SequenceStyle {
+    Arpeggio,
+    Strum,
+}
+
+impl SequenceStyle {
+    pub const ALL: [SequenceStyle; 2] = [SequenceStyle::Arpeggio, SequenceStyle::Strum];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SequenceStyle::Arpeggio => "One at a time",
+            SequenceStyle::Strum => "Strum",
+        }
+    }
+}
+
+/*
+   Purpose: Which end of the tuning "Play all" starts from
+   Notes: This goes by pitch rather than the order the strings are listed in, which isn't always lowest to highest
+          (e.g. a ukulele's high G).
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SequenceOrder {
+    LowToHigh,
+    HighToLow,
+}
+
+impl SequenceOrder {
+    pub const ALL: [SequenceOrder; 2] = [SequenceOrder::LowToHigh, SequenceOrder::HighToLow];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SequenceOrder::LowToHigh => "Low to high",
+            SequenceOrder::HighToLow => "High to low",
+        }
+    }
+}
+
+/*
+   Purpose: How "Play all" plays a whole tuning
+   Notes: gap is the time in seconds from one string to the next in an arpeggio. A strum always uses STRUM_STAGGER.
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SequenceSettings {
+    pub style: SequenceStyle,
+    pub order: SequenceOrder,
+    pub gap: f64,
+}
+
+impl Default for SequenceSettings {
+    fn default() -> Self {
+        Self {
+            style: SequenceStyle::Arpeggio,
+            order: SequenceOrder::LowToHigh,
+            gap: 1.0,
+        }
+    }
+}
+
+// SOUND ------------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: A reference tone, generated as it plays and shaped by an envelope. Either a set length or endless for a drone.
+   Notes: Every tone ends with the envelope's release, whether it runs its course, is stopped or is replaced by another,
+          so none of them stop dead with a click.
+*/
+struct Tone {
+    voice: Voice,
+    envelope: Envelope,
+    sample_rate: u32,
+    // Samples of silence left before the tone starts, for the strings after the first when a whole tuning is played
+    wait: u64,
+    gain: f64,
+    // Samples played so far
+    age: u64,
+    // When the tone lets go by itself, None for a drone
+    release_at: Option<u64>,
+    // The envelope's level and the tone's age when it was let go
+    released: Option<(f64, u64)>,
+    // Dropped along with the tone, which is how the player can tell it has finished
+    _alive: Arc<()>,
+}
+
+/*
+   Purpose: What generates a tone's waveform
+   Notes: The oscillators are based on rodio's SineWave (https://docs.rs/rodio/latest/src/rodio/source/sine.rs.html), but that
+          works its phase out from a sample count in f32, which drifts out of tune once a drone has been going for a while.
+          Here the phase is kept as a fraction of a cycle that wraps around at 1, so it stays exact and continues smoothly from one
+          sample to the next however long the tone plays. There are no buffer edges for it to click at.
+*/
+enum Voice {
+    Oscillator {
+        phase: f64,
+        // How far through a cycle each sample moves, i.e. frequency / sample_rate
+        step: f64,
+        // The amplitude of each harmonic, scaled so the waveform peaks at 1 like a sine
+        harmonics: Vec<f64>,
+    },
+    String(PluckedString),
+}
+
+impl Tone {
+    fn new(
+        pitch: Pitch,
+        reference_a4: f64,
+        settings: ToneSettings,
+        sample_rate: u32,
+        drone: bool,
+        alive: Arc<()>,
+    ) -> Self {
+        let frequency = pitch.frequency(reference_a4);
+        let step = frequency / sample_rate as f64;
+        let (voice, envelope, seconds) = match settings.timbre {
+            Timbre::Sine => (
+                Voice::Oscillator {
+                    phase: 0.0,
+                    step,
+                    harmonics: vec![1.0],
+                },
+                OSCILLATOR_ENVELOPE,
+                TONE_SECONDS,
+            ),
+            Timbre::Harmonics => (
+                Voice::Oscillator {
+                    phase: 0.0,
+                    step,
+                    harmonics: normalised_harmonics(step),
+                },
+                OSCILLATOR_ENVELOPE,
+                TONE_SECONDS,
+            ),
+            Timbre::Plucked => (
+                Voice::String(PluckedString::new(frequency, sample_rate, settings, drone)),
+                PLUCKED_ENVELOPE,
+                settings.decay,
+            ),
+        };
+
+        // The release is part of the tone's length rather than added on the end
+        let release_at = ((seconds - envelope.release).max(0.0) * sample_rate as f64) as u64;
+        Self {
+            voice,
+            envelope,
+            sample_rate,
+            wait: 0,
+            gain: 1.0,
+            age: 0,
+            release_at: (!drone).then_some(release_at),
+            released: None,
+            _alive: alive,
+        }
+    }
+
+    fn seconds(&self, samples: u64) -> f64 {
+        samples as f64 / self.sample_rate as f64
+    }
+
+    fn samples(&self, seconds: f64) -> u64 {
+        (seconds.max(0.0) * self.sample_rate as f64) as u64
+    }
+
+    /*
+       Purpose: Holds the tone back for the given number of seconds before it starts
+    */
+    fn delayed(mut self, seconds: f64) -> Self {
+        self.wait = self.samples(seconds);
+        self
+    }
+
+    /*
+       Purpose: Lets go of the tone after the given number of seconds at the latest, even if it's a drone
+    */
+    fn held_for(mut self, seconds: f64) -> Self {
+        let hold = self.samples(seconds);
+        self.release_at = Some(
+            self.release_at
+                .map_or(hold, |release_at| release_at.min(hold)),
+        );
+        self
+    }
+
+    fn scaled(mut self, gain: f64) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    /*
+       Purpose: Starts the release from wherever the envelope has got to
+    */
+    fn release(&mut self) {
+        if self.released.is_none() {
+            self.released = Some((self.envelope.level(self.seconds(self.age)), self.age));
+        }
+    }
+
+    /*
+       Purpose: The next sample, or None once the release has finished
+    */
+    fn next(&mut self) -> Option<f64> {
+        if self.wait > 0 {
+            // A tone that's let go before it has started is never heard
+            if self.released.is_some() {
+                return None;
+            }
+            self.wait -= 1;
+            return Some(0.0);
+        }
+        if self.release_at == Some(self.age) {
+            self.release();
+        }
+        let level = match self.released {
+            None => self.envelope.level(self.seconds(self.age)),
+            Some((level, released_at)) => {
+                let since = self.seconds(self.age - released_at);
+                if since >= self.envelope.release {
+                    return None;
+                }
+                level * (1.0 - since / self.envelope.release)
+            }
+        };
+        self.age += 1;
+        Some(self.voice.next() * level * self.gain)
+    }
+}
+
+impl Voice {
+    fn next(&mut self) -> f64 {
+        match self {
+            Voice::Oscillator {
+                phase,
+                step,
+                harmonics,
+            } => {
+                let sample = additive_sample(harmonics, *phase);
+                *phase = (*phase + *step).fract();
+                sample
+            }
+            Voice::String(string) => string.next(),
+        }
+    }
+}
+
+/*
+   Purpose: HARMONIC_AMPLITUDES without the harmonics at or above the Nyquist frequency, scaled so the waveform peaks at 1
+   Notes: The peak depends on how the harmonics line up, so it's found by sampling one cycle.
+*/
+fn normalised_harmonics(step: f64) -> Vec<f64> {
+    let harmonics: Vec<f64> = HARMONIC_AMPLITUDES
+        .iter()
+        .enumerate()
+        .take_while(|(i, _)| (*i + 1) as f64 * step < 0.5)
+        .map(|(_, amplitude)| *amplitude)
+        .collect();
+    let peak = (0..1024)
+        .map(|i| additive_sample(&harmonics, i as f64 / 1024.0).abs())
+        .fold(0.0, f64::max);
+    harmonics
+        .iter()
+        .map(|amplitude| amplitude / peak.max(f64::MIN_POSITIVE))
+        .collect()
+}
+
+fn additive_sample(harmonics: &[f64], phase: f64) -> f64 {
+    harmonics
+        .iter()
+        .enumerate()
+        .map(|(i, amplitude)| amplitude * ((i + 1) as f64 * phase * 2.0 * PI).sin())
+        .sum()
+}
+
+// ENVELOPE ---------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: An attack, decay, sustain, release envelope. The times are in seconds and sustain is a level from 0 to 1.
+   Notes: Based on https://en.wikipedia.org/wiki/Envelope_(music)
+          Even a few milliseconds of attack and release take the edges off a tone, which would otherwise click as it starts and stops.
+          The release is handled by Tone, since it starts from wherever the envelope had got to.
+*/
+#[derive(Copy, Clone)]
+struct Envelope {
+    attack: f64,
+    decay: f64,
+    sustain: f64,
+    release: f64,
+}
+
+impl Envelope {
+    /*
+       Purpose: The level the given number of seconds into the tone, before it's released
+    */
+    fn level(&self, seconds: f64) -> f64 {
+        if seconds < self.attack {
+            seconds / self.attack
+        } else if seconds < self.attack + self.decay {
+            1.0 - (1.0 - self.sustain) * (seconds - self.attack) / self.decay
+        } else {
+            self.sustain
+        }
+    }
+}
+
+// PLUCKED STRING ---------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: A Karplus-Strong plucked string
+   Notes: Based on https://en.wikipedia.org/wiki/Karplus%E2%80%93Strong_string_synthesis and Jaffe & Smith,
+          "Extensions of the Karplus-Strong Plucked-String Algorithm" (1983): https://www.jstor.org/stable/3680063
+          A burst of noise circulates round a delay line one period long, passing through a lowpass filter each time round.
+          The filter takes the upper harmonics away faster than the lower ones, like a real string.
+          The loop is also delayed by the lowpass filter, and a whole number of samples is only accurate to a few cents on the
+          high strings, so an allpass filter makes up the fractional part of the period. Without it the tone would be out of tune.
+          The lowpass is (1 - stretch) * x[n] + stretch * x[n - 1]. A stretch of 0.5 is the original algorithm's average,
+          smaller ones let the harmonics ring longer.
+*/
+struct PluckedString {
+    delay: Vec<f64>,
+    position: usize,
+    stretch: f64,
+    // Gain applied every time round the loop, which sets how fast the note dies away
+    loss: f64,
+    allpass: f64,
+    previous_input: f64,
+    allpass_input: f64,
+    allpass_output: f64,
+    noise: u32,
+    // A drone plucks again every this many samples, a single note never does
+    pluck_interval: Option<u32>,
+    since_pluck: u32,
+}
+
+impl PluckedString {
+    fn new(frequency: f64, sample_rate: u32, settings: ToneSettings, drone: bool) -> Self {
+        let brightness = settings
+            .brightness
+            .clamp(*BRIGHTNESS_RANGE.start(), *BRIGHTNESS_RANGE.end());
+        let decay = match drone {
+            true => DRONE_DECAY,
+            false => settings
+                .decay
+                .clamp(*DECAY_RANGE.start(), *DECAY_RANGE.end()),
+        };
+        let stretch = 0.5 - 0.45 * brightness;
+
+        // The lowpass filter's delay at the fundamental, from its phase response
+        let omega = 2.0 * PI * frequency / sample_rate as f64;
+        let lowpass_delay =
+            (stretch * omega.sin()).atan2(1.0 - stretch + stretch * omega.cos()) / omega;
+        // The loop needs to be exactly one period long. The allpass takes the fractional part, kept between 0.1 and 1.1 samples
+        // where its delay is most accurate.
+        let period = sample_rate as f64 / frequency;
+        let length = ((period - lowpass_delay - 0.1).floor() as usize).max(1);
+        let fraction = period - lowpass_delay - length as f64;
+        let allpass = (1.0 - fraction) / (1.0 + fraction);
+
+        // The lowpass also quietens the fundamental a little each time round, which the loss makes up for
+        let lowpass_gain = ((1.0 - stretch + stretch * omega.cos()).powi(2)
+            + (stretch * omega.sin()).powi(2))
+        .sqrt();
+        let loss = (10f64.powf(-3.0 / (decay * frequency)) / lowpass_gain).min(1.0);
+
+        let mut string = Self {
+            delay: vec![0.0; length],
+            position: 0,
+            stretch,
+            loss,
+            allpass,
+            previous_input: 0.0,
+            allpass_input: 0.0,
+            allpass_output: 0.0,
+            noise: 0x1234_5678,
+            pluck_interval: drone.then_some((DRONE_PLUCK_INTERVAL * sample_rate as f64) as u32),
+            since_pluck: 0,
+        };
+        string.pluck();
+        string
+    }
+
+    /*
+       Purpose: Adds a burst of noise to the string, scaled to peak at 1 like a sine
+       Notes: The mean is taken off so the note doesn't start with a thump, and since the noise is added to what's already ringing,
+              plucking again during a drone carries on from the old note rather than cutting it off.
+    */
+    fn pluck(&mut self) {
+        let burst: Vec<f64> = (0..self.delay.len())
+            .map(|_| {
+                // A linear congruential generator is plenty for noise: https://en.wikipedia.org/wiki/Linear_congruential_generator
+                self.noise = self
+                    .noise
+                    .wrapping_mul(1_664_525)
+                    .wrapping_add(1_013_904_223);
+                (self.noise >> 8) as f64 / (1 << 24) as f64 - 0.5
+            })
+            .collect();
+        let mean = burst.iter().sum::<f64>() / burst.len() as f64;
+        let peak = burst
+            .iter()
+            .map(|x| (x - mean).abs())
+            .fold(f64::MIN_POSITIVE, f64::max);
+        for (sample, noise) in self.delay.iter_mut().zip(&burst) {
+            *sample += (noise - mean) / peak;
+        }
+        self.since_pluck = 0;
+    }
+
+    fn next(&mut self) -> f64 {
+        if let Some(interval) = self.pluck_interval {
+            self.since_pluck += 1;
+            if self.since_pluck >= interval {
+                self.pluck();
+            }
+        }
+
+        let output = self.delay[self.position];
+        let filtered = (1.0 - self.stretch) * output + self.stretch * self.previous_input;
+        self.previous_input = output;
+        let input = filtered * self.loss;
+        // First order allpass: https://ccrma.stanford.edu/~jos/pasp/First_Order_Allpass_Interpolation.html
+        let delayed =
+            self.allpass * input + self.allpass_input - self.allpass * self.allpass_output;
+        self.allpass_input = input;
+        self.allpass_output = delayed;
+        self.delay[self.position] = delayed;
+        self.position = (self.position + 1) % self.delay.len();
+        output
+    }
+}
+
+// MIXER ------------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: What the player asks of the mixer on the audio thread
+*/
+enum Command {
+    // Releases whatever is playing and starts the new tones, which can each be delayed
+    Play(Vec<Tone>),
+    Release,
+    Volume(f64),
+}
+
+/*
+   Purpose: Mixes the tones that are playing, sets the volume and limits the result. This is what rodio actually plays.
+   Notes: It never runs out, so one mixer serves every tone played on an output stream. That way a tone that's being released
+          and the one replacing it go through the same limiter, and their sum can't go over the ceiling either.
+          Commands arrive over a channel rather than a mutex, so the audio thread never waits on the gui.
+          Volume changes are smoothed over VOLUME_SMOOTHING_SECONDS so dragging the slider doesn't crackle.
+*/
+struct Mixer {
+    commands: Receiver<Command>,
+    tones: Vec<Tone>,
+    sample_rate: u32,
+    gain: f64,
+    target_gain: f64,
+    smoothing: f64,
+    limiter: Limiter,
+}
+
+impl Mixer {
+    fn new(commands: Receiver<Command>, sample_rate: u32, volume_db: f64) -> Self {
+        Self {
+            commands,
+            tones: Vec::new(),
+            sample_rate,
+            gain: volume_gain(volume_db),
+            target_gain: volume_gain(volume_db),
+            smoothing: smoothing_coefficient(VOLUME_SMOOTHING_SECONDS, sample_rate),
+            limiter: Limiter::new(sample_rate),
+        }
+    }
+}
+
+impl Iterator for Mixer {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        while let Ok(command) = self.commands.try_recv() {
+            match command {
+                Command::Play(tones) => {
+                    self.tones.iter_mut().for_each(Tone::release);
+                    self.tones.extend(tones);
+                }
+                Command::Release => self.tones.iter_mut().for_each(Tone::release),
+                Command::Volume(volume_db) => self.target_gain = volume_gain(volume_db),
+            }
+        }
+
+        let mut sum = 0.0;
+        self.tones.retain_mut(|tone| match tone.next() {
+            Some(sample) => {
+                sum += sample;
+                true
+            }
+            None => false,
+        });
+        self.gain += (self.target_gain - self.gain) * self.smoothing;
+        Some(self.limiter.process(sum * self.gain) as f32)
+    }
+}
+
+impl Source for Mixer {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/*
+   Purpose: Turns a volume in dBFS into the gain it's applied with
+   Notes: Decibels follow how loud things sound, so equal steps on the slider sound like equal steps in loudness.
+          Based on https://en.wikipedia.org/wiki/DBFS
+*/
+fn volume_gain(volume_db: f64) -> f64 {
+    let volume_db = volume_db.clamp(*VOLUME_RANGE_DB.start(), *VOLUME_RANGE_DB.end());
+    10f64.powf(volume_db / 20.0)
+}
+
+/*
+   Purpose: The coefficient of a one-pole smoother that covers most of the way to its target in the given time
+*/
+fn smoothing_coefficient(seconds: f64, sample_rate: u32) -> f64 {
+    1.0 - (-1.0 / (seconds * sample_rate as f64)).exp()
+}
+
+/*
+   Purpose: A hard limiter that keeps every sample within LIMITER_CEILING_DB
+   Notes: Based on https://en.wikipedia.org/wiki/Dynamic_range_compression#Limiting
+          The attack is instant, so there's nothing to overshoot: a sample that would go over turns the gain down just enough,
+          and the gain then recovers over about LIMITER_RECOVERY_SECONDS. Anything that isn't a number is replaced by silence.
+*/
+struct Limiter {
+    ceiling: f64,
+    reduction: f64,
+    recovery: f64,
+}
+
+impl Limiter {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            ceiling: 10f64.powf(LIMITER_CEILING_DB / 20.0),
+            reduction: 1.0,
+            recovery: smoothing_coefficient(LIMITER_RECOVERY_SECONDS, sample_rate),
+        }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        if !sample.is_finite() {
+            return 0.0;
+        }
+        self.reduction += (1.0 - self.reduction) * self.recovery;
+        if (sample * self.reduction).abs() > self.ceiling {
+            self.reduction = self.ceiling / sample.abs();
+        }
+        (sample * self.reduction).clamp(-self.ceiling, self.ceiling)
+    }
+}
+
+// TONE PLAYER ------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Plays reference tones in the background, either one at a time or a whole tuning in sequence
+   Notes: Based on documentation: https://docs.rs/rodio/latest/rodio/
+          The output stream is opened on the first tone and kept for as long as the player lives. rodio stops playing as soon as
+          the stream is dropped, which is why tones used to only be heard if the gui thread slept until they finished.
+          The stream is reopened when a different output device is chosen or the system default moves elsewhere, and once more
+          if its mixer has gone when a tone is sent. rodio doesn't say when a stream's device goes away, so reopening is what
+          shows the device is missing instead of the player going silent. A tone that still fails resets the stream for next time.
+          Tones are generated at the device's own sample rate so they don't get resampled, and all of them go through one Mixer.
+*/
+pub struct TonePlayer {
+    output: Option<Output>,
+    // The pitches that were last played, each with a handle that only its tone shares while it's still playing
+    playing: Vec<(Pitch, Arc<()>)>,
+    volume_db: f64,
+    drone: bool,
+}
+
+/*
+   Purpose: An open output stream, the device it was opened for and the way to send its mixer commands
+   Notes: device is what was asked for (None for the default), name is the device that was actually opened.
+*/
+struct Output {
+    device: Option<String>,
+    name: String,
+    _stream: OutputStream,
+    commands: Sender<Command>,
+    sample_rate: u32,
+}
+
+impl Output {
+    fn open(device: Option<&str>, volume_db: f64) -> Result<Self, GuitarToolsError> {
+        let (stream, handle, sample_rate, name) = open_output_stream(device)?;
+        let (commands, receiver) = mpsc::channel();
+        // The mixer plays on rodio's own thread, so nothing here waits for the sound
+        handle
+            .play_raw(Mixer::new(receiver, sample_rate, volume_db))
+            .map_err(GuitarToolsError::stream)?;
+        Ok(Self {
+            device: device.map(str::to_string),
+            name,
+            _stream: stream,
+            commands,
+            sample_rate,
+        })
+    }
+
+    /*
+       Purpose: Whether this is still the stream to play on: it's for the device asked for, and if that's the default, the
+                system default hasn't moved to another device since it was opened
+       Notes: A chosen device isn't looked for, since listing the devices opens every one of them (including this one).
+    */
+    fn is_current(&self, device: Option<&str>) -> bool {
+        self.device.as_deref() == device
+            && (device.is_some() || default_output_device_name().as_ref() == Some(&self.name))
+    }
+}
+
+impl TonePlayer {
+    pub fn new(volume_db: f64) -> Self {
+        Self {
+            output: None,
+            playing: Vec::new(),
+            volume_db,
+            drone: false,
+        }
+    }
+
+    /*
+       Purpose: Starts playing the given pitch on the chosen output device and returns straight away
+       Notes: Whatever was playing is released, so clicking through the strings quickly doesn't queue them up. None uses the default device.
+    */
+    pub fn play(
+        &mut self,
+        pitch: Pitch,
+        reference_a4: f64,
+        tone: ToneSettings,
+        output_device: Option<&str>,
+    ) -> Result<(), GuitarToolsError> {
+        self.play_all(
+            &[pitch],
+            reference_a4,
+            tone,
+            SequenceSettings::default(),
+            output_device,
+        )
+    }
+
+    /*
+       Purpose: Starts playing every string of a tuning, one after another or strummed, and returns straight away
+       Notes: In an arpeggio each string is let go as the next one starts, so they can be told apart, and the last one plays out
+              as a single note would (or keeps going in drone mode). A strum lets them all ring together like an open chord,
+              each one quieter so the chord is about as loud as a single note.
+    */
+    pub fn play_all(
+        &mut self,
+        strings: &[Pitch],
+        reference_a4: f64,
+        tone: ToneSettings,
+        sequence: SequenceSettings,
+        output_device: Option<&str>,
+    ) -> Result<(), GuitarToolsError> {
+        self.playing.clear();
+        let result = self.start(strings, reference_a4, tone, sequence, output_device);
+        if result.is_err() {
+            self.output = None;
+        }
+        result
+    }
+
+    fn start(
+        &mut self,
+        strings: &[Pitch],
+        reference_a4: f64,
+        tone: ToneSettings,
+        sequence: SequenceSettings,
+        output_device: Option<&str>,
+    ) -> Result<(), GuitarToolsError> {
+        if !self
+            .output
+            .as_ref()
+            .is_some_and(|output| output.is_current(output_device))
+        {
+            // The old stream has to let go of its device before the device can be opened again
+            self.output = None;
+        }
+        let mut reopened = false;
+        loop {
+            let output = match self.output.take() {
+                Some(output) => output,
+                None => {
+                    reopened = true;
+                    Output::open(output_device, self.volume_db)?
+                }
+            };
+            let (tones, playing) =
+                self.tones(strings, reference_a4, tone, sequence, output.sample_rate);
+            match output.commands.send(Command::Play(tones)) {
+                Ok(()) => {
+                    self.output = Some(output);
+                    self.playing = playing;
+                    return Ok(());
+                }
+                // The mixer has gone, most likely along with its device. Opening it again says whether the device is still there.
+                Err(_) if !reopened => drop(output),
+                Err(_) => return Err(GuitarToolsError::stream("the output stream has stopped")),
+            }
+        }
+    }
+
+    /*
+       Purpose: Builds the tones for a tuning's strings in the order and with the timing they're played, each with its handle
+    */
+    fn tones(
+        &self,
+        strings: &[Pitch],
+        reference_a4: f64,
+        tone: ToneSettings,
+        sequence: SequenceSettings,
+        sample_rate: u32,
+    ) -> (Vec<Tone>, Vec<(Pitch, Arc<()>)>) {
+        let mut strings = strings.to_vec();
+        strings.sort_by_key(Pitch::midi);
+        if sequence.order == SequenceOrder::HighToLow {
+            strings.reverse();
+        }
+        let gap = sequence.gap.clamp(*GAP_RANGE.start(), *GAP_RANGE.end());
+        let count = strings.len();
+        let mut tones = Vec::with_capacity(count);
+        let mut playing = Vec::with_capacity(count);
+        for (i, pitch) in strings.into_iter().enumerate() {
+            let alive = Arc::new(());
+            let note = Tone::new(
+                pitch,
+                reference_a4,
+                tone,
+                sample_rate,
+                self.drone,
+                alive.clone(),
+            );
+            tones.push(match sequence.style {
+                SequenceStyle::Arpeggio if i + 1 < count => {
+                    note.delayed(i as f64 * gap).held_for(gap)
+                }
+                SequenceStyle::Arpeggio => note.delayed(i as f64 * gap),
+                SequenceStyle::Strum => note
+                    .delayed(i as f64 * STRUM_STAGGER)
+                    .scaled(1.0 / (count as f64).sqrt()),
+            });
+            playing.push((pitch, alive));
+        }
+        (tones, playing)
+    }
+
+    /*
+       Purpose: Fades out whatever is playing
+    */
+    pub fn stop(&mut self) {
+        if let Some(output) = &self.output {
+            // If the mixer has gone there's nothing playing to stop anyway
+            let _ = output.commands.send(Command::Release);
+        }
+        self.playing.clear();
+    }
+
+    /*
+       Purpose: The pitches that are playing or still to come, in the order they're played. Empty once they've finished or been stopped.
+    */
+    pub fn playing(&self) -> Vec<Pitch> {
+        self.playing
+            .iter()
+            .filter(|(_, alive)| Arc::strong_count(alive) > 1)
+            .map(|(pitch, _)| *pitch)
+            .collect()
+    }
+
+    /*
+       Purpose: Sets the volume in dBFS, including for the tone that's already playing
+    */
+    pub fn set_volume_db(&mut self, volume_db: f64) {
+        self.volume_db = volume_db;
+        if let Some(output) = &self.output {
+            let _ = output.commands.send(Command::Volume(volume_db));
+        }
+    }
+
+    pub fn drone(&self) -> bool {
+        self.drone
+    }
+
+    /*
+       Purpose: Turns drone mode on or off. In drone mode tones keep playing until they're stopped or another note is picked.
+       Notes: Turning it off stops a drone that's playing, since it would otherwise never end.
+    */
+    pub fn set_drone(&mut self, drone: bool) {
+        if self.drone && !drone {
+            self.stop();
+        }
+        self.drone = drone;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 44100;
+
+    fn ceiling() -> f64 {
+        10f64.powf(LIMITER_CEILING_DB / 20.0)
+    }
+
+    #[test]
+    fn limiter_keeps_loud_signals_under_the_ceiling() {
+        let mut limiter = Limiter::new(SAMPLE_RATE);
+        for i in 0..SAMPLE_RATE {
+            let sample = 4.0 * (2.0 * PI * 110.0 * i as f64 / SAMPLE_RATE as f64).sin();
+            assert!(limiter.process(sample).abs() <= ceiling());
+        }
+    }
+
+    #[test]
+    fn limiter_leaves_quiet_signals_alone_and_recovers() {
+        let mut limiter = Limiter::new(SAMPLE_RATE);
+        assert_eq!(limiter.process(0.5), 0.5);
+        assert!(limiter.process(10.0) <= ceiling());
+        // Well after LIMITER_RECOVERY_SECONDS the gain is back to where it was
+        for _ in 0..SAMPLE_RATE {
+            limiter.process(0.0);
+        }
+        assert!((limiter.process(0.5) - 0.5).abs() < 1e-3);
+        assert_eq!(limiter.process(f64::NAN), 0.0);
+    }
+
+    #[test]
+    fn a_full_volume_strum_stays_under_the_ceiling() {
+        let (commands, receiver) = mpsc::channel();
+        let mut mixer = Mixer::new(receiver, SAMPLE_RATE, *VOLUME_RANGE_DB.end());
+        let tone = ToneSettings {
+            timbre: Timbre::Harmonics,
+            ..ToneSettings::default()
+        };
+        let tones = ["E2", "A2", "D3", "G3", "B3", "E4"]
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                Tone::new(
+                    text.parse().unwrap(),
+                    440.0,
+                    tone,
+                    SAMPLE_RATE,
+                    false,
+                    Arc::new(()),
+                )
+                .delayed(i as f64 * STRUM_STAGGER)
+            })
+            .collect();
+        commands.send(Command::Play(tones)).unwrap();
+        let peak = mixer
+            .by_ref()
+            .take(SAMPLE_RATE as usize)
+            .fold(0.0f64, |peak, sample| peak.max((sample as f64).abs()));
+        assert!(peak > 0.5 && peak <= ceiling() + 1e-6, "peak was {}", peak);
+    }
+}
@@ -0,0 +1,591 @@
+use crate::{
+    audio_io::{open_input_stream, InputSettings},
+    error::GuitarToolsError,
+    notes::{describe_pitch, Accidental, NoteName, Pitch, STANDARD_A4},
+    pitch::{cents_between, DetectorKind, PitchEstimate, CHROMATIC_LOWEST_FREQUENCY},
+    spectrogram::{Spectrogram, SpectrogramSettings},
+    visualise::AnalysisView,
+};
+use std::{
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+pub const IN_TUNE_CENTS: f64 = 3.0;
+const ANALYSIS_INTERVAL: Duration = Duration::from_millis(200);
+// How many periods of the lowest expected note each analysis window should hold, and the limits on its length in seconds
+const PERIODS_PER_WINDOW: f64 = 30.0;
+const WINDOW_SECONDS_RANGE: RangeInclusive<f64> = 0.25..=1.5;
+// A string being tuned can start well flat of its target, so the window is sized for a few semitones below it
+const TARGET_FLAT_MARGIN: f64 = 0.8;
+// How much of each new reading is blended into the smoothed one, and how big a jump skips the smoothing
+const SMOOTHING_FACTOR: f64 = 0.4;
+const SMOOTHING_RESET_CENTS: f64 = 30.0;
+
+// Live Tuning ------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Keeps track of the background listening thread and the latest reading it produced
+   Notes: The input stream lives on its own thread so the gui never has to wait on the microphone.
+          The audio callback pushes into a lock-free ring buffer and the thread drains it every ANALYSIS_INTERVAL.
+          If the input can't be opened or stops working, listening stops and the error is kept for the gui to show.
+          Everything but the options is made afresh for each run, so a thread that's winding down can't write into the next one.
+*/
+pub struct LiveTuner {
+    thread: Option<JoinHandle<()>>,
+    listening: Arc<AtomicBool>,
+    options: Arc<Mutex<TunerOptions>>,
+    reading: Arc<Mutex<String>>,
+    latest: Arc<Mutex<Option<TunerReading>>>,
+    view: Arc<Mutex<Option<Arc<AnalysisView>>>>,
+    spectrogram: Arc<Mutex<Option<Spectrogram>>>,
+    error: Arc<Mutex<Option<GuitarToolsError>>>,
+}
+
+/*
+   Purpose: The latest pitch reading in numbers, for displays like the needle that don't want to parse the text reading
+   Notes: pitch is the target note, or the nearest note in chromatic mode. cents is how far the recorded frequency is from it.
+          The frequency and cents are smoothed over the last few readings so the display doesn't jitter.
+*/
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TunerReading {
+    pub frequency: f64,
+    pub pitch: Pitch,
+    pub cents: f64,
+    pub confidence: f64,
+}
+
+/*
+   Purpose: The choices the gui makes that the listening thread reads on every analysis pass
+   Notes: target of None means chromatic.
+          lowest_string is the lowest open string of the chosen instrument, which sizes the window in chromatic mode.
+          visualise asks for an AnalysisView on every pass. It's off unless the view is open since it costs an extra fourier transform.
+          spectrogram is None unless the spectrogram is open. Changing its settings starts it over.
+*/
+#[derive(Copy, Clone)]
+struct TunerOptions {
+    target: Option<Pitch>,
+    detector: DetectorKind,
+    reference_a4: f64,
+    lowest_string: Pitch,
+    visualise: bool,
+    spectrogram: Option<SpectrogramSettings>,
+}
+
+impl TunerOptions {
+    /*
+       Purpose: The lowest frequency the current analysis needs to be able to pick up
+    */
+    fn lowest_frequency(&self) -> f64 {
+        match self.target {
+            Some(pitch) => pitch.frequency(self.reference_a4) * TARGET_FLAT_MARGIN,
+            None => self.lowest_string.frequency(self.reference_a4),
+        }
+        .max(CHROMATIC_LOWEST_FREQUENCY)
+    }
+}
+
+impl Default for LiveTuner {
+    fn default() -> Self {
+        Self {
+            thread: None,
+            listening: Arc::new(AtomicBool::new(false)),
+            options: Arc::new(Mutex::new(TunerOptions {
+                target: None,
+                detector: DetectorKind::default(),
+                reference_a4: STANDARD_A4,
+                lowest_string: Pitch::new(NoteName::E, Accidental::Natural, 2),
+                visualise: false,
+                spectrogram: None,
+            })),
+            reading: Arc::new(Mutex::new("Result: N/A".to_string())),
+            latest: Arc::new(Mutex::new(None)),
+            view: Arc::new(Mutex::new(None)),
+            spectrogram: Arc::new(Mutex::new(None)),
+            error: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl LiveTuner {
+    pub fn is_listening(&self) -> bool {
+        self.listening.load(AtomicOrdering::Relaxed)
+    }
+
+    pub fn reading(&self) -> String {
+        self.reading.lock().unwrap().clone()
+    }
+
+    /*
+       Purpose: The latest smoothed reading, or None if nothing has been heard clearly since the last pass
+    */
+    pub fn latest_reading(&self) -> Option<TunerReading> {
+        *self.latest.lock().unwrap()
+    }
+
+    /*
+       Purpose: Sets the note the recorded pitch is compared against. None means chromatic.
+    */
+    pub fn set_target(&self, target: Option<Pitch>) {
+        self.options.lock().unwrap().target = target;
+    }
+
+    pub fn detector(&self) -> DetectorKind {
+        self.options.lock().unwrap().detector
+    }
+
+    /*
+       Purpose: Switches which pitch detection algorithm the listening thread uses
+       Notes: This takes effect on the next analysis pass, so there's no need to restart listening.
+    */
+    pub fn set_detector(&self, detector: DetectorKind) {
+        self.options.lock().unwrap().detector = detector;
+    }
+
+    /*
+       Purpose: Sets the concert pitch the target notes and cents readout are measured against
+    */
+    pub fn set_reference_a4(&self, reference_a4: f64) {
+        self.options.lock().unwrap().reference_a4 = reference_a4;
+    }
+
+    /*
+       Purpose: Sets the lowest open string of the instrument being tuned
+       Notes: Lower strings need a longer analysis window, so this trades a bit of responsiveness for a stable reading on basses.
+    */
+    pub fn set_lowest_string(&self, lowest_string: Pitch) {
+        self.options.lock().unwrap().lowest_string = lowest_string;
+    }
+
+    /*
+       Purpose: Turns the analysis view on or off
+    */
+    pub fn set_visualise(&self, visualise: bool) {
+        self.options.lock().unwrap().visualise = visualise;
+    }
+
+    /*
+       Purpose: What the last analysis pass saw, for plotting. None until the view has been turned on and a pass has run.
+    */
+    pub fn analysis_view(&self) -> Option<Arc<AnalysisView>> {
+        self.view.lock().unwrap().clone()
+    }
+
+    /*
+       Purpose: Turns the spectrogram on with the given settings, or off with None
+    */
+    pub fn set_spectrogram(&self, settings: Option<SpectrogramSettings>) {
+        self.options.lock().unwrap().spectrogram = settings;
+    }
+
+    /*
+       Purpose: Lets the gui look at the spectrogram without copying it. Returns None if it's off or listening hasn't started.
+    */
+    pub fn with_spectrogram<T>(&self, f: impl FnOnce(&Spectrogram) -> T) -> Option<T> {
+        self.spectrogram.lock().unwrap().as_ref().map(f)
+    }
+
+    /*
+       Purpose: What went wrong with the input or the analysis, if anything has since listening last started
+    */
+    pub fn error(&self) -> Option<GuitarToolsError> {
+        self.error.lock().unwrap().clone()
+    }
+
+    pub fn clear_error(&self) {
+        *self.error.lock().unwrap() = None;
+    }
+
+    /*
+       Purpose: Spawns the listening thread
+       Notes: The previous thread is waited for first, so its input stream is closed before the new one is opened.
+              Devices that only one stream can have open at a time (e.g. ALSA's hw: devices) would otherwise be busy.
+              Every start gets a fresh flag and fresh readings so the previous thread can't be revived or write into this run.
+              on_update is called from the listening thread whenever the reading changes, e.g. to ask the gui to repaint.
+              The input is only read here, so listening has to be restarted for a different input to take effect.
+    */
+    pub fn start(&mut self, input: InputSettings, on_update: impl Fn() + Send + 'static) {
+        self.stop();
+        if let Some(thread) = self.thread.take() {
+            // A thread that panicked has nothing left to clean up
+            let _ = thread.join();
+        }
+        self.listening = Arc::new(AtomicBool::new(true));
+        self.reading = Arc::new(Mutex::new("Result: Listening...".to_string()));
+        self.latest = Arc::new(Mutex::new(None));
+        self.view = Arc::new(Mutex::new(None));
+        self.spectrogram = Arc::new(Mutex::new(None));
+        self.error = Arc::new(Mutex::new(None));
+
+        let shared = Shared {
+            listening: self.listening.clone(),
+            options: self.options.clone(),
+            reading: self.reading.clone(),
+            latest: self.latest.clone(),
+            view: self.view.clone(),
+            spectrogram: self.spectrogram.clone(),
+            error: self.error.clone(),
+        };
+        self.thread = Some(thread::spawn(move || listen(shared, input, on_update)));
+    }
+
+    /*
+       Purpose: Tells the listening thread to finish, waking it if it's waiting for more audio
+    */
+    pub fn stop(&mut self) {
+        self.listening.store(false, AtomicOrdering::Relaxed);
+        if let Some(thread) = &self.thread {
+            thread.thread().unpark();
+        }
+    }
+}
+
+/*
+   Purpose: The parts of LiveTuner the listening thread writes to or reads from
+*/
+struct Shared {
+    listening: Arc<AtomicBool>,
+    options: Arc<Mutex<TunerOptions>>,
+    reading: Arc<Mutex<String>>,
+    latest: Arc<Mutex<Option<TunerReading>>>,
+    view: Arc<Mutex<Option<Arc<AnalysisView>>>>,
+    spectrogram: Arc<Mutex<Option<Spectrogram>>>,
+    error: Arc<Mutex<Option<GuitarToolsError>>>,
+}
+
+/*
+   Purpose: Body of the listening thread. Keeps the most recent stretch of audio and analyses it until told to stop.
+   Notes: The length of that stretch follows analysis_window, so it changes as the target or instrument does.
+          A pass that can't be analysed is reported and skipped, while an input that stops working ends the thread.
+*/
+fn listen(shared: Shared, input: InputSettings, on_update: impl Fn()) {
+    // Called from the audio thread, so it can only flag the problem for this loop and the gui to pick up
+    let on_error = {
+        let error = shared.error.clone();
+        let listening = shared.listening.clone();
+        move |err| {
+            *error.lock().unwrap() = Some(err);
+            listening.store(false, AtomicOrdering::Relaxed);
+        }
+    };
+    let (stream, sample_rate, mut consumer) = match open_input_stream(&input, on_error) {
+        Ok(input) => input,
+        Err(err) => {
+            *shared.reading.lock().unwrap() = "Result: N/A".to_string();
+            *shared.error.lock().unwrap() = Some(err);
+            shared.listening.store(false, AtomicOrdering::Relaxed);
+            on_update();
+            return;
+        }
+    };
+
+    let longest_window = (sample_rate as f64 * WINDOW_SECONDS_RANGE.end()) as usize;
+    let mut samples: Vec<f32> = Vec::with_capacity(longest_window * 2);
+    let mut smoothed: Option<TunerReading> = None;
+
+    while shared.listening.load(AtomicOrdering::Relaxed) {
+        // Parked rather than asleep so stop() can wake it straight away
+        thread::park_timeout(ANALYSIS_INTERVAL);
+        if !shared.listening.load(AtomicOrdering::Relaxed) {
+            break;
+        }
+
+        let options = *shared.options.lock().unwrap();
+        let new_samples: Vec<f32> = consumer.pop_iter().collect();
+        update_spectrogram(
+            &shared.spectrogram,
+            options.spectrogram,
+            sample_rate,
+            &new_samples,
+        );
+
+        // Enough is always kept for the longest window, so switching to a lower string doesn't have to wait for audio to build up
+        samples.extend(new_samples);
+        if samples.len() > longest_window {
+            samples.drain(..samples.len() - longest_window);
+        }
+
+        let window = analysis_window(sample_rate, options.lowest_frequency());
+        if samples.len() < window {
+            continue;
+        }
+        let samples = &samples[samples.len() - window..];
+
+        let analysed = options
+            .detector
+            .build()
+            .detect(samples, sample_rate)
+            .and_then(|estimate| {
+                let view = match options.visualise {
+                    true => Some(AnalysisView::new(samples, sample_rate, estimate.as_ref())?),
+                    false => None,
+                };
+                Ok((estimate, view))
+            });
+        let (estimate, view) = match analysed {
+            Ok(analysed) => analysed,
+            Err(err) => {
+                *shared.error.lock().unwrap() = Some(err);
+                on_update();
+                continue;
+            }
+        };
+        clear_analysis_error(&shared.error);
+
+        let (result, new_reading) =
+            analyse(estimate.as_ref(), options.target, options.reference_a4);
+        smoothed =
+            new_reading.map(|new_reading| smooth(smoothed, new_reading, options.reference_a4));
+        if let Some(view) = view {
+            *shared.view.lock().unwrap() = Some(Arc::new(view));
+        }
+
+        *shared.reading.lock().unwrap() = result;
+        *shared.latest.lock().unwrap() = smoothed;
+        on_update();
+    }
+
+    drop(stream);
+    // Lets the gui show why listening stopped if the input broke
+    on_update();
+}
+
+/*
+   Purpose: Forgets an analysis error once a pass goes through
+   Notes: Errors from the input itself are left alone, they stay until listening is started again.
+*/
+fn clear_analysis_error(error: &Mutex<Option<GuitarToolsError>>) {
+    let mut error = error.lock().unwrap();
+    if matches!(*error, Some(GuitarToolsError::Analysis(_))) {
+        *error = None;
+    }
+}
+
+/*
+   Purpose: Feeds the newly recorded samples to the spectrogram, starting it over if its settings changed
+*/
+fn update_spectrogram(
+    spectrogram: &Mutex<Option<Spectrogram>>,
+    settings: Option<SpectrogramSettings>,
+    sample_rate: u32,
+    new_samples: &[f32],
+) {
+    let mut spectrogram = spectrogram.lock().unwrap();
+    let settings = match settings {
+        Some(settings) => settings,
+        None => {
+            *spectrogram = None;
+            return;
+        }
+    };
+    if spectrogram.as_ref().map(Spectrogram::settings) != Some(settings) {
+        *spectrogram = Some(Spectrogram::new(settings, sample_rate));
+    }
+    if let Some(spectrogram) = spectrogram.as_mut() {
+        spectrogram.push(new_samples);
+    }
+}
+
+/*
+   Purpose: How many samples to analyse so the lowest expected note gets PERIODS_PER_WINDOW periods
+   Notes: Higher notes get a shorter window, so the reading follows the string more closely. Kept within WINDOW_SECONDS_RANGE.
+          For example a low B on a 5-string bass (31 Hz) gets about a second, while a guitar's high E gets a quarter of one.
+*/
+pub fn analysis_window(sample_rate: u32, lowest_frequency: f64) -> usize {
+    let seconds = (PERIODS_PER_WINDOW / lowest_frequency)
+        .clamp(*WINDOW_SECONDS_RANGE.start(), *WINDOW_SECONDS_RANGE.end());
+    (seconds * sample_rate as f64) as usize
+}
+
+/*
+   Purpose: Blends a new reading into the previous one with an exponential moving average over cents
+   Notes: A different note or a jump of more than SMOOTHING_RESET_CENTS starts over, so switching strings shows up straight away.
+*/
+fn smooth(previous: Option<TunerReading>, new: TunerReading, reference_a4: f64) -> TunerReading {
+    let previous = match previous {
+        Some(previous)
+            if previous.pitch == new.pitch
+                && (new.cents - previous.cents).abs() <= SMOOTHING_RESET_CENTS =>
+        {
+            previous
+        }
+        _ => return new,
+    };
+
+    let cents = previous.cents + SMOOTHING_FACTOR * (new.cents - previous.cents);
+    TunerReading {
+        frequency: new.pitch.frequency(reference_a4) * 2f64.powf(cents / 1200.0),
+        cents,
+        ..new
+    }
+}
+
+/*
+   Purpose: Turns what the detector found in the latest audio into the reading shown in the gui
+   Notes: Also gives the reading in numbers, or None when no clear pitch was heard.
+*/
+pub fn analyse(
+    estimate: Option<&PitchEstimate>,
+    target: Option<Pitch>,
+    reference_a4: f64,
+) -> (String, Option<TunerReading>) {
+    let estimate = match estimate {
+        Some(estimate) => estimate,
+        None => return ("Result: No clear pitch detected".to_string(), None),
+    };
+
+    let (pitch, cents) = match target {
+        Some(pitch) => (
+            pitch,
+            cents_between(estimate.frequency, pitch.frequency(reference_a4)),
+        ),
+        None => Pitch::nearest(estimate.frequency, reference_a4),
+    };
+    let reading = TunerReading {
+        frequency: estimate.frequency,
+        pitch,
+        cents,
+        confidence: estimate.confidence,
+    };
+
+    let mut result = match target {
+        Some(pitch) => tune_by_recording(estimate.frequency, pitch, reference_a4),
+        None => tune_chromatic(estimate.frequency, reference_a4),
+    };
+    result += format!(" Confidence: {:.0}%", estimate.confidence * 100.0).as_str();
+
+    if !estimate.harmonics.is_empty() {
+        let harmonics: Vec<String> = estimate
+            .harmonics
+            .iter()
+            .map(|harmonic| format!("{:.1} Hz", harmonic))
+            .collect();
+        result += "\nHarmonics: ";
+        result += harmonics.join(", ").as_str();
+    }
+    (result, Some(reading))
+}
+
+/*
+   Purpose: Names the recorded note, no matter the string or tuning
+   Notes: This will be sent to the gui to update the live reading.
+*/
+pub fn tune_chromatic(frequency: f64, reference_a4: f64) -> String {
+    format!(
+        "Result: (Recorded Pitch: {:.2} Hz): {}",
+        frequency,
+        describe_pitch(frequency, reference_a4)
+    )
+}
+
+/*
+   Purpose: Compares the recorded frequency to the target note. Then outputs a string telling the user whether they need to tune up or down
+   Notes: This will be sent to the gui to update the live reading.
+*/
+pub fn tune_by_recording(frequency: f64, pitch: Pitch, reference_a4: f64) -> String {
+    let target = pitch.frequency(reference_a4);
+    let cents = cents_between(frequency, target);
+
+    let mut result = format!(
+        "Result: (Target Pitch: {} at {:.2} Hz Recorded Pitch: {:.2} Hz, {:+.1} cents): ",
+        pitch, target, frequency, cents
+    );
+
+    result += advice(cents);
+    result
+}
+
+/*
+   Purpose: Tells the user which way to turn the peg for a string that's the given number of cents off
+   Notes: Anything within IN_TUNE_CENTS counts as in tune, since that's below what most people can hear.
+*/
+pub fn advice(cents: f64) -> &'static str {
+    if cents.abs() <= IN_TUNE_CENTS {
+        "\"Perfect!\""
+    } else if cents > 0.0 {
+        "\"You shoud loosen your string!\""
+    } else {
+        "\"You should tighen your string!\""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pitch(text: &str) -> Pitch {
+        text.parse().unwrap()
+    }
+
+    fn estimate(frequency: f64) -> PitchEstimate {
+        PitchEstimate {
+            frequency,
+            confidence: 0.9,
+            harmonics: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn analysis_window_grows_for_low_strings() {
+        assert_eq!(analysis_window(44100, 82.41), 16053);
+        // A 5-string bass's low B gets close to a second, a guitar's high E gets the shortest window
+        assert_eq!(analysis_window(48000, 30.87), 46647);
+        assert_eq!(analysis_window(48000, 329.63), 12000);
+        assert_eq!(analysis_window(48000, 10.0), 72000);
+    }
+
+    #[test]
+    fn readings_are_against_the_target_at_the_reference() {
+        let (_, reading) = analyse(Some(&estimate(110.0)), Some(pitch("A2")), 432.0);
+        let reading = reading.unwrap();
+        assert_eq!(reading.pitch, pitch("A2"));
+        assert!((reading.cents - 31.77).abs() < 0.01);
+
+        // Without a target the nearest note is named instead
+        let (text, reading) = analyse(Some(&estimate(440.0 * 0.99)), None, STANDARD_A4);
+        assert_eq!(reading.unwrap().pitch, pitch("A4"));
+        assert!(text.contains("A4 -17.4 cents"), "{}", text);
+
+        assert!(analyse(None, Some(pitch("A2")), STANDARD_A4).1.is_none());
+    }
+
+    #[test]
+    fn smoothing_starts_over_for_a_new_note_or_a_big_jump() {
+        let reading = |text: &str, cents: f64| {
+            analyse(
+                Some(&estimate(
+                    pitch(text).frequency(STANDARD_A4) * 2f64.powf(cents / 1200.0),
+                )),
+                Some(pitch(text)),
+                STANDARD_A4,
+            )
+            .1
+            .unwrap()
+        };
+        let first = reading("E2", 10.0);
+        let blended = smooth(Some(first), reading("E2", 0.0), STANDARD_A4);
+        assert!((blended.cents - 6.0).abs() < 1e-9);
+        assert!(
+            (cents_between(blended.frequency, pitch("E2").frequency(STANDARD_A4)) - 6.0).abs()
+                < 1e-9
+        );
+
+        assert_eq!(
+            smooth(Some(first), reading("A2", 0.0), STANDARD_A4).pitch,
+            pitch("A2")
+        );
+        assert!((smooth(Some(first), reading("E2", -25.0), STANDARD_A4).cents + 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn advice_says_which_way_to_turn() {
+        assert_eq!(advice(2.9), "\"Perfect!\"");
+        assert_eq!(advice(-3.0), "\"Perfect!\"");
+        assert_eq!(advice(12.0), "\"You shoud loosen your string!\"");
+        assert_eq!(advice(-12.0), "\"You should tighen your string!\"");
+    }
+}
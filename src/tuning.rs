@@ -0,0 +1,780 @@
+use crate::{
+    notes::{
+        Accidental::{Flat, Natural, Sharp},
+        NoteName::{A, B, C, D, E, F, G},
+        ParsePitchError, Pitch,
+    },
+    settings::config_directory,
+};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt, fs, ops::RangeInclusive, path::PathBuf};
+
+pub const STRING_COUNT_RANGE: RangeInclusive<usize> = 1..=12;
+const CUSTOM_TUNINGS_FILE: &str = "tunings.toml";
+const CUSTOM_TUNINGS_BACKUP_FILE: &str = "tunings.toml.bak";
+
+// TUNINGS ----------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: The instrument profiles a tuning can belong to
+   Notes: The pages only list tunings for the instrument that's picked, and the tuner uses it to know how low a string can go.
+          Guitar keeps its old name so tunings files saved before the other profiles existed still load.
+*/
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Instrument {
+    #[default]
+    Guitar,
+    SevenStringGuitar,
+    EightStringGuitar,
+    BaritoneGuitar,
+    Bass,
+    FiveStringBass,
+    SixStringBass,
+    Ukulele,
+    Mandolin,
+    Banjo,
+    Violin,
+    Viola,
+    Cello,
+}
+
+impl Instrument {
+    pub const ALL: [Instrument; 13] = [
+        Instrument::Guitar,
+        Instrument::SevenStringGuitar,
+        Instrument::EightStringGuitar,
+        Instrument::BaritoneGuitar,
+        Instrument::Bass,
+        Instrument::FiveStringBass,
+        Instrument::SixStringBass,
+        Instrument::Ukulele,
+        Instrument::Mandolin,
+        Instrument::Banjo,
+        Instrument::Violin,
+        Instrument::Viola,
+        Instrument::Cello,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Instrument::Guitar => "6-string guitar",
+            Instrument::SevenStringGuitar => "7-string guitar",
+            Instrument::EightStringGuitar => "8-string guitar",
+            Instrument::BaritoneGuitar => "Baritone guitar",
+            Instrument::Bass => "4-string bass",
+            Instrument::FiveStringBass => "5-string bass",
+            Instrument::SixStringBass => "6-string bass",
+            Instrument::Ukulele => "Ukulele",
+            Instrument::Mandolin => "Mandolin",
+            Instrument::Banjo => "5-string banjo",
+            Instrument::Violin => "Violin",
+            Instrument::Viola => "Viola",
+            Instrument::Cello => "Cello",
+        }
+    }
+
+    /*
+       Purpose: Anything about the instrument that changes how its tunings should be read
+    */
+    pub fn tip(&self) -> Option<&'static str> {
+        match self {
+            Instrument::Ukulele => Some("Standard ukulele tuning is re-entrant: the G string is tuned higher than the C next to it."),
+            Instrument::Mandolin => Some("Each note is a course of two strings. Tune both strings of a course to the same note."),
+            Instrument::Banjo => Some("The short 5th string is listed first. It's the high G tuned with your thumb, not the lowest string."),
+            _ => None,
+        }
+    }
+}
+
+/*
+   Purpose: A named set of open string pitches for an instrument
+   Notes: Strings are listed in the order they sit on the instrument, starting from the one closest to the player's chin (the low E on a guitar).
+          That's usually lowest to highest, but not for re-entrant tunings such as the ukulele's high G, so nothing should assume the first string is the lowest.
+*/
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tuning {
+    pub name: String,
+    #[serde(default)]
+    pub instrument: Instrument,
+    pub strings: Vec<Pitch>,
+}
+
+impl Tuning {
+    pub fn new(name: &str, instrument: Instrument, strings: &[Pitch]) -> Self {
+        Self {
+            name: name.to_string(),
+            instrument,
+            strings: strings.to_vec(),
+        }
+    }
+
+    /*
+       Purpose: Builds a tuning from what the user typed into the tuning editor
+       Notes: Each string is written like "E2" or "Eb3". Surrounding whitespace in the name is dropped.
+    */
+    pub fn parse(
+        name: &str,
+        instrument: Instrument,
+        strings: &[String],
+    ) -> Result<Self, TuningError> {
+        let strings = strings
+            .iter()
+            .enumerate()
+            .map(|(index, text)| {
+                text.parse()
+                    .map_err(|error| TuningError::InvalidString { index, error })
+            })
+            .collect::<Result<Vec<Pitch>, TuningError>>()?;
+
+        let tuning = Tuning::new(name.trim(), instrument, &strings);
+        tuning.validate()?;
+        Ok(tuning)
+    }
+
+    /*
+       Purpose: Whether the strings go up and down in pitch across the instrument rather than only rising
+    */
+    pub fn is_reentrant(&self) -> bool {
+        self.strings
+            .windows(2)
+            .any(|pair| pair[1].midi() < pair[0].midi())
+    }
+
+    pub fn validate(&self) -> Result<(), TuningError> {
+        if self.name.trim().is_empty() {
+            return Err(TuningError::MissingName);
+        }
+        if !STRING_COUNT_RANGE.contains(&self.strings.len()) {
+            return Err(TuningError::StringCount(self.strings.len()));
+        }
+        Ok(())
+    }
+}
+
+/*
+   Purpose: Why a tuning was rejected
+   Notes: String indexes start at 0 here and are shown to the user starting at 1.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TuningError {
+    MissingName,
+    DuplicateName(String),
+    // A built-in tuning for the same instrument already has this name
+    BuiltInName(String),
+    StringCount(usize),
+    InvalidString {
+        index: usize,
+        error: ParsePitchError,
+    },
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TuningError::MissingName => write!(f, "the tuning needs a name"),
+            TuningError::DuplicateName(name) => {
+                write!(f, "there is more than one tuning called \"{}\"", name)
+            }
+            TuningError::BuiltInName(name) => write!(
+                f,
+                "there is already a built-in tuning called \"{}\" for this instrument",
+                name
+            ),
+            TuningError::StringCount(count) => write!(
+                f,
+                "a tuning needs between {} and {} strings, this one has {}",
+                STRING_COUNT_RANGE.start(),
+                STRING_COUNT_RANGE.end(),
+                count
+            ),
+            TuningError::InvalidString { index, error } => {
+                write!(f, "string {}: {}", index + 1, error)
+            }
+        }
+    }
+}
+
+impl Error for TuningError {}
+
+/*
+   Purpose: The tunings that ship with the app
+   Notes: The tunings listed here are some of the common tunings found on https://muted.io/guitar-tuning-chart/ and https://theacousticguitarist.com/alternate-tunings-for-acoustic-guitar/
+          Adding a tuning here adds it to both the "Tune by ear" and "Tune by recording" pages.
+*/
+pub fn built_in_tunings() -> Vec<Tuning> {
+    vec![
+        Tuning::new(
+            "Standard Tuning",
+            Instrument::Guitar,
+            &[
+                Pitch::new(E, Natural, 2),
+                Pitch::new(A, Natural, 2),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(B, Natural, 3),
+                Pitch::new(E, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Half Step Down Tuning",
+            Instrument::Guitar,
+            &[
+                Pitch::new(E, Flat, 2),
+                Pitch::new(A, Flat, 2),
+                Pitch::new(D, Flat, 3),
+                Pitch::new(G, Flat, 3),
+                Pitch::new(B, Flat, 3),
+                Pitch::new(E, Flat, 4),
+            ],
+        ),
+        Tuning::new(
+            "Full Step Down Tuning",
+            Instrument::Guitar,
+            &[
+                Pitch::new(D, Natural, 2),
+                Pitch::new(G, Natural, 2),
+                Pitch::new(C, Natural, 3),
+                Pitch::new(F, Natural, 3),
+                Pitch::new(A, Natural, 3),
+                Pitch::new(D, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Drop D Tuning",
+            Instrument::Guitar,
+            &[
+                Pitch::new(D, Natural, 2),
+                Pitch::new(A, Natural, 2),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(B, Natural, 3),
+                Pitch::new(E, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Drop C Tuning",
+            Instrument::Guitar,
+            &[
+                Pitch::new(C, Natural, 2),
+                Pitch::new(G, Natural, 2),
+                Pitch::new(C, Natural, 3),
+                Pitch::new(F, Natural, 3),
+                Pitch::new(A, Natural, 3),
+                Pitch::new(D, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Open E Tuning",
+            Instrument::Guitar,
+            &[
+                Pitch::new(E, Natural, 2),
+                Pitch::new(B, Natural, 2),
+                Pitch::new(E, Natural, 3),
+                Pitch::new(G, Sharp, 3),
+                Pitch::new(B, Natural, 3),
+                Pitch::new(E, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Open D Tuning",
+            Instrument::Guitar,
+            &[
+                Pitch::new(D, Natural, 2),
+                Pitch::new(A, Natural, 2),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(F, Sharp, 3),
+                Pitch::new(A, Natural, 3),
+                Pitch::new(D, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Open G Tuning",
+            Instrument::Guitar,
+            &[
+                Pitch::new(D, Natural, 2),
+                Pitch::new(G, Natural, 2),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(B, Natural, 3),
+                Pitch::new(D, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "DADGAD Tuning",
+            Instrument::Guitar,
+            &[
+                Pitch::new(D, Natural, 2),
+                Pitch::new(A, Natural, 2),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(A, Natural, 3),
+                Pitch::new(D, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Standard Tuning",
+            Instrument::SevenStringGuitar,
+            &[
+                Pitch::new(B, Natural, 1),
+                Pitch::new(E, Natural, 2),
+                Pitch::new(A, Natural, 2),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(B, Natural, 3),
+                Pitch::new(E, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Drop A Tuning",
+            Instrument::SevenStringGuitar,
+            &[
+                Pitch::new(A, Natural, 1),
+                Pitch::new(E, Natural, 2),
+                Pitch::new(A, Natural, 2),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(B, Natural, 3),
+                Pitch::new(E, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Standard Tuning",
+            Instrument::EightStringGuitar,
+            &[
+                Pitch::new(F, Sharp, 1),
+                Pitch::new(B, Natural, 1),
+                Pitch::new(E, Natural, 2),
+                Pitch::new(A, Natural, 2),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(B, Natural, 3),
+                Pitch::new(E, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Drop E Tuning",
+            Instrument::EightStringGuitar,
+            &[
+                Pitch::new(E, Natural, 1),
+                Pitch::new(B, Natural, 1),
+                Pitch::new(E, Natural, 2),
+                Pitch::new(A, Natural, 2),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(B, Natural, 3),
+                Pitch::new(E, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "B Standard Tuning",
+            Instrument::BaritoneGuitar,
+            &[
+                Pitch::new(B, Natural, 1),
+                Pitch::new(E, Natural, 2),
+                Pitch::new(A, Natural, 2),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(F, Sharp, 3),
+                Pitch::new(B, Natural, 3),
+            ],
+        ),
+        Tuning::new(
+            "A Standard Tuning",
+            Instrument::BaritoneGuitar,
+            &[
+                Pitch::new(A, Natural, 1),
+                Pitch::new(D, Natural, 2),
+                Pitch::new(G, Natural, 2),
+                Pitch::new(C, Natural, 3),
+                Pitch::new(E, Natural, 3),
+                Pitch::new(A, Natural, 3),
+            ],
+        ),
+        Tuning::new(
+            "Standard Tuning",
+            Instrument::Bass,
+            &[
+                Pitch::new(E, Natural, 1),
+                Pitch::new(A, Natural, 1),
+                Pitch::new(D, Natural, 2),
+                Pitch::new(G, Natural, 2),
+            ],
+        ),
+        Tuning::new(
+            "Drop D Tuning",
+            Instrument::Bass,
+            &[
+                Pitch::new(D, Natural, 1),
+                Pitch::new(A, Natural, 1),
+                Pitch::new(D, Natural, 2),
+                Pitch::new(G, Natural, 2),
+            ],
+        ),
+        Tuning::new(
+            "Standard Tuning",
+            Instrument::FiveStringBass,
+            &[
+                Pitch::new(B, Natural, 0),
+                Pitch::new(E, Natural, 1),
+                Pitch::new(A, Natural, 1),
+                Pitch::new(D, Natural, 2),
+                Pitch::new(G, Natural, 2),
+            ],
+        ),
+        Tuning::new(
+            "Standard Tuning",
+            Instrument::SixStringBass,
+            &[
+                Pitch::new(B, Natural, 0),
+                Pitch::new(E, Natural, 1),
+                Pitch::new(A, Natural, 1),
+                Pitch::new(D, Natural, 2),
+                Pitch::new(G, Natural, 2),
+                Pitch::new(C, Natural, 3),
+            ],
+        ),
+        Tuning::new(
+            "Standard Tuning",
+            Instrument::Ukulele,
+            &[
+                Pitch::new(G, Natural, 4),
+                Pitch::new(C, Natural, 4),
+                Pitch::new(E, Natural, 4),
+                Pitch::new(A, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Standard Tuning (Low G)",
+            Instrument::Ukulele,
+            &[
+                Pitch::new(G, Natural, 3),
+                Pitch::new(C, Natural, 4),
+                Pitch::new(E, Natural, 4),
+                Pitch::new(A, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "D Tuning",
+            Instrument::Ukulele,
+            &[
+                Pitch::new(A, Natural, 4),
+                Pitch::new(D, Natural, 4),
+                Pitch::new(F, Sharp, 4),
+                Pitch::new(B, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Baritone Tuning",
+            Instrument::Ukulele,
+            &[
+                Pitch::new(D, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(B, Natural, 3),
+                Pitch::new(E, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Standard Tuning",
+            Instrument::Mandolin,
+            &[
+                Pitch::new(G, Natural, 3),
+                Pitch::new(D, Natural, 4),
+                Pitch::new(A, Natural, 4),
+                Pitch::new(E, Natural, 5),
+            ],
+        ),
+        Tuning::new(
+            "Open G Tuning",
+            Instrument::Banjo,
+            &[
+                Pitch::new(G, Natural, 4),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(B, Natural, 3),
+                Pitch::new(D, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Double C Tuning",
+            Instrument::Banjo,
+            &[
+                Pitch::new(G, Natural, 4),
+                Pitch::new(C, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(C, Natural, 4),
+                Pitch::new(D, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Standard Tuning",
+            Instrument::Violin,
+            &[
+                Pitch::new(G, Natural, 3),
+                Pitch::new(D, Natural, 4),
+                Pitch::new(A, Natural, 4),
+                Pitch::new(E, Natural, 5),
+            ],
+        ),
+        Tuning::new(
+            "Standard Tuning",
+            Instrument::Viola,
+            &[
+                Pitch::new(C, Natural, 3),
+                Pitch::new(G, Natural, 3),
+                Pitch::new(D, Natural, 4),
+                Pitch::new(A, Natural, 4),
+            ],
+        ),
+        Tuning::new(
+            "Standard Tuning",
+            Instrument::Cello,
+            &[
+                Pitch::new(C, Natural, 2),
+                Pitch::new(G, Natural, 2),
+                Pitch::new(D, Natural, 3),
+                Pitch::new(A, Natural, 3),
+            ],
+        ),
+    ]
+}
+
+/*
+   Purpose: The lowest open string among the given tunings for an instrument
+   Notes: The tuner uses this in chromatic mode to size its analysis window, so custom tunings should be passed in too.
+*/
+pub fn lowest_string(tunings: &[Tuning], instrument: Instrument) -> Option<Pitch> {
+    tunings
+        .iter()
+        .filter(|tuning| tuning.instrument == instrument)
+        .flat_map(|tuning| tuning.strings.iter().copied())
+        .min_by_key(|pitch| pitch.midi())
+}
+
+// CUSTOM TUNINGS ---------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: The layout of the custom tunings file
+   Notes: Each tuning is a [[tuning]] table, e.g.
+              [[tuning]]
+              name = "Nick Drake"
+              instrument = "Guitar"
+              strings = ["C2", "G2", "C3", "F3", "C4", "E4"]
+          It's read with each table left as a plain toml::Value, so the tunings can be checked one at a time.
+*/
+#[derive(Serialize, Deserialize)]
+struct CustomTuningsFile<T> {
+    #[serde(default = "Vec::new", rename = "tuning")]
+    tunings: Vec<T>,
+}
+
+/*
+   Purpose: The user's own tunings as they were read, and why any others in the file were left out
+   Notes: Each skipped entry names the tuning's place in the file, counting from 1, e.g. "tuning 3: string 2: ..."
+*/
+#[derive(Debug, Default)]
+pub struct CustomTunings {
+    pub tunings: Vec<Tuning>,
+    pub skipped: Vec<String>,
+}
+
+/*
+   Purpose: Reads the user's own tunings from the config directory
+   Notes: No file just means the user hasn't made any yet. A file that can't be read or isn't TOML at all is an error.
+          Otherwise each tuning is read on its own, so one with a bad note or a name that's taken only leaves that one out.
+          Either way the file isn't the same as what's loaded, so back_up_custom_tunings should be called before saving over it.
+*/
+pub fn load_custom_tunings() -> Result<CustomTunings, Box<dyn Error>> {
+    let path = match custom_tunings_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(CustomTunings::default()),
+    };
+
+    let text = fs::read_to_string(&path)
+        .map_err(|err| format!("could not read {}: {}", path.display(), err))?;
+    parse_custom_tunings(&text)
+        .map_err(|err| format!("{} is not a valid tunings file: {}", path.display(), err).into())
+}
+
+/*
+   Purpose: Reads the tunings out of the text of a tunings file, skipping any that aren't valid
+*/
+pub fn parse_custom_tunings(text: &str) -> Result<CustomTunings, toml::de::Error> {
+    let file: CustomTuningsFile<toml::Value> = toml::from_str(text)?;
+    let built_in = built_in_tunings();
+    let mut loaded = CustomTunings::default();
+    for (index, value) in file.tunings.into_iter().enumerate() {
+        let tuning = value.try_into::<Tuning>().map_err(|err| err.to_string());
+        match tuning.and_then(|tuning| {
+            check_custom_tuning(&tuning, &loaded.tunings, &built_in)
+                .map(|()| tuning)
+                .map_err(|err| err.to_string())
+        }) {
+            Ok(tuning) => loaded.tunings.push(tuning),
+            Err(err) => loaded
+                .skipped
+                .push(format!("tuning {}: {}", index + 1, err)),
+        }
+    }
+    Ok(loaded)
+}
+
+/*
+   Purpose: Moves the custom tunings file aside to tunings.toml.bak, replacing any older backup
+   Notes: Done before the first save over a file that didn't load cleanly, so tunings that were left out aren't lost.
+          Returns where the file went, or None if there wasn't one.
+*/
+pub fn back_up_custom_tunings() -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let path = match custom_tunings_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(None),
+    };
+    let backup = path.with_file_name(CUSTOM_TUNINGS_BACKUP_FILE);
+    fs::rename(&path, &backup)?;
+    Ok(Some(backup))
+}
+
+pub fn save_custom_tunings(tunings: &[Tuning]) -> Result<(), Box<dyn Error>> {
+    validate_tunings(tunings)?;
+    let path = custom_tunings_path().ok_or("could not find a config directory")?;
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let file = CustomTuningsFile {
+        tunings: tunings.to_vec(),
+    };
+    fs::write(path, toml::to_string(&file)?)?;
+    Ok(())
+}
+
+/*
+   Purpose: Checks every tuning on its own, then that no two of them share a name
+   Notes: The error says which tuning is at fault, counting from 1.
+*/
+pub fn validate_tunings(tunings: &[Tuning]) -> Result<(), Box<dyn Error>> {
+    let built_in = built_in_tunings();
+    for (index, tuning) in tunings.iter().enumerate() {
+        check_custom_tuning(tuning, &tunings[..index], &built_in)
+            .map_err(|err| format!("tuning {}: {}", index + 1, err))?;
+    }
+    Ok(())
+}
+
+/*
+   Purpose: Checks one custom tuning, and that its name isn't taken by one before it
+   Notes: A built-in tuning's name can't be reused for the same instrument either, since pages pick tunings by name.
+*/
+fn check_custom_tuning(
+    tuning: &Tuning,
+    earlier: &[Tuning],
+    built_in: &[Tuning],
+) -> Result<(), TuningError> {
+    tuning.validate()?;
+    if earlier
+        .iter()
+        .any(|other| other.name.trim() == tuning.name.trim())
+    {
+        return Err(TuningError::DuplicateName(tuning.name.clone()));
+    }
+    if built_in.iter().any(|other| {
+        other.instrument == tuning.instrument && other.name.trim() == tuning.name.trim()
+    }) {
+        return Err(TuningError::BuiltInName(tuning.name.clone()));
+    }
+    Ok(())
+}
+
+fn custom_tunings_path() -> Option<PathBuf> {
+    config_directory().map(|directory| directory.join(CUSTOM_TUNINGS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuning(name: &str, instrument: Instrument) -> Tuning {
+        let strings: Vec<Pitch> = ["D2", "A2", "D3", "G3", "A3", "D4"]
+            .iter()
+            .map(|text| text.parse().unwrap())
+            .collect();
+        Tuning::new(name, instrument, &strings)
+    }
+
+    fn validation_error(tunings: &[Tuning]) -> String {
+        validate_tunings(tunings).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn built_in_tunings_are_valid() {
+        for tuning in built_in_tunings() {
+            assert_eq!(tuning.validate(), Ok(()), "{}", tuning.name);
+        }
+    }
+
+    #[test]
+    fn custom_tunings_need_their_own_names() {
+        let ok = [
+            tuning("Mine", Instrument::Guitar),
+            tuning("Also mine", Instrument::Guitar),
+        ];
+        assert!(validate_tunings(&ok).is_ok());
+
+        let duplicate = [
+            tuning("Mine", Instrument::Guitar),
+            tuning(" Mine ", Instrument::Bass),
+        ];
+        assert_eq!(
+            validation_error(&duplicate),
+            "tuning 2: there is more than one tuning called \" Mine \""
+        );
+    }
+
+    #[test]
+    fn custom_tunings_cant_reuse_a_built_in_name() {
+        assert_eq!(
+            validation_error(&[tuning("DADGAD Tuning", Instrument::Guitar)]),
+            "tuning 1: there is already a built-in tuning called \"DADGAD Tuning\" for this instrument"
+        );
+        // Only the guitar has a built-in DADGAD, so it's free for other instruments
+        assert!(validate_tunings(&[tuning("DADGAD Tuning", Instrument::Bass)]).is_ok());
+    }
+
+    #[test]
+    fn a_bad_tuning_only_leaves_itself_out() {
+        let loaded = parse_custom_tunings(
+            r#"
+            [[tuning]]
+            name = "Nick Drake"
+            strings = ["C2", "G2", "C3", "F3", "C4", "E4"]
+
+            [[tuning]]
+            name = "Typo"
+            strings = ["E2", "H2"]
+
+            [[tuning]]
+            name = "Standard Tuning"
+            strings = ["E2", "A2", "D3", "G3", "B3", "E4"]
+
+            [[tuning]]
+            name = "Nick Drake"
+            strings = ["C2", "G2"]
+
+            [[tuning]]
+            name = "Open C"
+            strings = ["C2", "G2", "C3", "G3", "C4", "E4"]
+            "#,
+        )
+        .unwrap();
+        let names: Vec<&str> = loaded
+            .tunings
+            .iter()
+            .map(|tuning| tuning.name.as_str())
+            .collect();
+        assert_eq!(names, ["Nick Drake", "Open C"]);
+        assert_eq!(loaded.skipped.len(), 3);
+        assert!(loaded.skipped[0].starts_with("tuning 2: "));
+        assert!(loaded.skipped[1].starts_with("tuning 3: there is already a built-in tuning"));
+        assert!(loaded.skipped[2].starts_with("tuning 4: there is more than one tuning"));
+    }
+
+    #[test]
+    fn a_file_that_isnt_toml_is_an_error() {
+        assert!(parse_custom_tunings("[[tuning]\nname = ").is_err());
+    }
+}
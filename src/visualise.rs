@@ -0,0 +1,118 @@
+use crate::{
+    audio_io::AudioData,
+    error::GuitarToolsError,
+    pitch::{find_harmonics, generate_fourier_transform, PitchEstimate, Spectrum},
+};
+use std::ops::RangeInclusive;
+
+pub const VIEW_LOWEST_FREQUENCY: f64 = 20.0;
+pub const VIEW_HIGHEST_FREQUENCY: f64 = 5000.0;
+// Anything quieter than this, relative to the loudest bin, is drawn at the floor
+pub const VIEW_FLOOR_DB: f64 = -90.0;
+const SPECTRUM_POINTS: usize = 600;
+const WAVEFORM_PERIODS: f64 = 4.0;
+const WAVEFORM_SECONDS_RANGE: RangeInclusive<f64> = 0.01..=0.1;
+
+// ANALYSIS VIEW ----------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Everything the analysis view plots for one analysis pass, already reduced to plot points
+   Notes: The waveform is (milliseconds, amplitude) and the spectrum is (log10 of Hz, dB below the loudest bin).
+          The spectrum uses a log frequency axis so every octave gets the same width, like the strings on a fretboard.
+          Built on the listening thread so the gui only has to draw a few hundred points.
+*/
+pub struct AnalysisView {
+    pub waveform: Vec<[f64; 2]>,
+    pub spectrum: Vec<[f64; 2]>,
+    pub fundamental: Option<f64>,
+    pub harmonics: Vec<f64>,
+}
+
+impl AnalysisView {
+    /*
+       Purpose: Builds the view from the analysed samples and whatever the detector found in them
+       Notes: Time-domain detectors don't list harmonics, so they're looked up in the spectrum instead.
+    */
+    pub fn new(
+        samples: &[f32],
+        sample_rate: u32,
+        estimate: Option<&PitchEstimate>,
+    ) -> Result<Self, GuitarToolsError> {
+        let spectrum = generate_fourier_transform(AudioData {
+            samples: samples.to_vec(),
+            sample_rate,
+        })?;
+
+        let fundamental = estimate.map(|estimate| estimate.frequency);
+        let harmonics = match estimate {
+            Some(estimate) if estimate.harmonics.is_empty() => {
+                find_harmonics(&spectrum, estimate.frequency)
+            }
+            Some(estimate) => estimate.harmonics.clone(),
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            waveform: waveform_points(samples, sample_rate, fundamental),
+            spectrum: spectrum_points(&spectrum),
+            fundamental,
+            harmonics,
+        })
+    }
+}
+
+/*
+   Purpose: The tail end of the recording, long enough to show WAVEFORM_PERIODS periods of the note when there is one
+*/
+fn waveform_points(samples: &[f32], sample_rate: u32, fundamental: Option<f64>) -> Vec<[f64; 2]> {
+    let seconds = fundamental
+        .map_or(*WAVEFORM_SECONDS_RANGE.end(), |frequency| {
+            WAVEFORM_PERIODS / frequency
+        })
+        .clamp(
+            *WAVEFORM_SECONDS_RANGE.start(),
+            *WAVEFORM_SECONDS_RANGE.end(),
+        );
+    let length = ((seconds * sample_rate as f64) as usize).min(samples.len());
+
+    samples[samples.len() - length..]
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| [i as f64 * 1000.0 / sample_rate as f64, *sample as f64])
+        .collect()
+}
+
+/*
+   Purpose: Reduces the spectrum to SPECTRUM_POINTS log-spaced bands between VIEW_LOWEST_FREQUENCY and VIEW_HIGHEST_FREQUENCY
+   Notes: Each band keeps its loudest bin, so narrow peaks don't disappear where many bins share a band.
+*/
+fn spectrum_points(spectrum: &Spectrum) -> Vec<[f64; 2]> {
+    let loudest = spectrum
+        .magnitudes
+        .iter()
+        .cloned()
+        .fold(f64::MIN_POSITIVE, f64::max);
+    let low = VIEW_LOWEST_FREQUENCY.log10();
+    let high = VIEW_HIGHEST_FREQUENCY.log10();
+    let band_edge =
+        |band: usize| 10f64.powf(low + (high - low) * band as f64 / SPECTRUM_POINTS as f64);
+
+    (0..SPECTRUM_POINTS)
+        .filter_map(|band| {
+            let start = spectrum.frequency_to_bin(band_edge(band));
+            let end = spectrum
+                .frequency_to_bin(band_edge(band + 1))
+                .max(start + 1)
+                .min(spectrum.magnitudes.len());
+            if start >= end {
+                return None;
+            }
+            let magnitude = spectrum.magnitudes[start..end]
+                .iter()
+                .cloned()
+                .fold(0.0, f64::max);
+            let db = (20.0 * (magnitude / loudest).log10()).max(VIEW_FLOOR_DB);
+            let centre = (band_edge(band) * band_edge(band + 1)).sqrt();
+            Some([centre.log10(), db])
+        })
+        .collect()
+}
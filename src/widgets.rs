@@ -0,0 +1,426 @@
+use eframe::egui::{
+    self,
+    plot::{GridInput, GridMark, Line, LineStyle, Plot, PlotPoints, VLine},
+    Color32, Pos2, Rect, Sense, Stroke, Vec2,
+};
+use guitar_tools::{
+    notes::{describe_pitch, Accidental, NoteName, Pitch},
+    spectrogram::{
+        Spectrogram, SpectrogramSettings, WindowFunction, SPECTROGRAM_FFT_SIZES,
+        SPECTROGRAM_HIGHEST_FREQUENCY, SPECTROGRAM_LOWEST_FREQUENCY, SPECTROGRAM_ROWS,
+    },
+    tuner::{TunerReading, IN_TUNE_CENTS},
+    visualise::{AnalysisView, VIEW_FLOOR_DB, VIEW_HIGHEST_FREQUENCY, VIEW_LOWEST_FREQUENCY},
+};
+use std::f32::consts::PI;
+
+// The needle covers this many cents either side of the target, anything further pins it to the end of the scale
+const SCALE_CENTS: f32 = 50.0;
+// How far either side of straight up the needle can swing, in radians
+const NEEDLE_SWING: f32 = PI / 3.0;
+// How long the needle takes to catch up with a new reading, in seconds
+const NEEDLE_EASING: f32 = 0.15;
+// How many stripe widths per second the strobe drifts for every cent out of tune
+const STROBE_SPEED: f32 = 0.1;
+const STROBE_STRIPES: usize = 12;
+
+const IN_TUNE_COLOR: Color32 = Color32::from_rgb(80, 200, 120);
+const OUT_OF_TUNE_COLOR: Color32 = Color32::from_rgb(230, 150, 60);
+
+// NEEDLE -----------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Draws an analog tuner style needle over a ±SCALE_CENTS scale, with the in-tune zone shaded
+   Notes: The needle eases towards each new reading instead of jumping, on top of the smoothing the tuner already does.
+          With no reading the needle rests in the middle and is greyed out.
+*/
+pub fn needle(ui: &mut egui::Ui, reading: Option<TunerReading>) {
+    let (rect, response) = ui.allocate_exact_size(Vec2::new(320.0, 170.0), Sense::hover());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+
+    let pivot = Pos2::new(rect.center().x, rect.bottom() - 20.0);
+    let radius = rect.height() - 40.0;
+    let point_at = |cents: f32, distance: f32| {
+        let angle = cents.clamp(-SCALE_CENTS, SCALE_CENTS) / SCALE_CENTS * NEEDLE_SWING;
+        pivot + distance * Vec2::new(angle.sin(), -angle.cos())
+    };
+
+    // In-tune zone, drawn as a fan of thin triangles
+    let zone = IN_TUNE_CENTS as f32;
+    let steps = 8;
+    for step in 0..steps {
+        let from = -zone + 2.0 * zone * step as f32 / steps as f32;
+        let to = -zone + 2.0 * zone * (step + 1) as f32 / steps as f32;
+        painter.add(egui::Shape::convex_polygon(
+            vec![pivot, point_at(from, radius), point_at(to, radius)],
+            IN_TUNE_COLOR.linear_multiply(0.25),
+            Stroke::NONE,
+        ));
+    }
+
+    // Scale, with a long tick every 10 cents and a short one every 5
+    for tick in (-SCALE_CENTS as i32..=SCALE_CENTS as i32).step_by(5) {
+        let length = if tick % 10 == 0 { 14.0 } else { 7.0 };
+        painter.line_segment(
+            [
+                point_at(tick as f32, radius - length),
+                point_at(tick as f32, radius),
+            ],
+            visuals.widgets.noninteractive.fg_stroke,
+        );
+        if tick % 25 == 0 {
+            painter.text(
+                point_at(tick as f32, radius + 10.0),
+                egui::Align2::CENTER_CENTER,
+                format!("{:+}", tick).replace("+0", "0"),
+                egui::FontId::proportional(12.0),
+                visuals.text_color(),
+            );
+        }
+    }
+
+    let target = reading.map_or(0.0, |reading| reading.cents as f32);
+    let cents = ui
+        .ctx()
+        .animate_value_with_time(response.id, target, NEEDLE_EASING);
+    let color = match reading {
+        Some(reading) if reading.cents.abs() <= IN_TUNE_CENTS => IN_TUNE_COLOR,
+        Some(_) => OUT_OF_TUNE_COLOR,
+        None => visuals.weak_text_color(),
+    };
+    painter.line_segment(
+        [pivot, point_at(cents, radius - 4.0)],
+        Stroke::new(3.0, color),
+    );
+    painter.circle_filled(pivot, 5.0, color);
+
+    let label = match reading {
+        Some(reading) => format!("{} {:+.1} cents", reading.pitch, reading.cents),
+        None => "--".to_string(),
+    };
+    painter.text(
+        Pos2::new(rect.center().x, rect.bottom() - 4.0),
+        egui::Align2::CENTER_BOTTOM,
+        label,
+        egui::FontId::proportional(16.0),
+        color,
+    );
+}
+
+// STROBE -----------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Draws a strobe tuner style band of stripes that drifts right when sharp, left when flat and stands still when in tune
+   Notes: Based on how mechanical strobe tuners work: https://en.wikipedia.org/wiki/Electronic_tuner#Strobe_tuners
+          The drift speed is proportional to the cents, so it shows far smaller errors than the needle can.
+          The stripe offset is kept in egui's memory between frames, and the page keeps repainting while the strobe is moving.
+*/
+pub fn strobe(ui: &mut egui::Ui, reading: Option<TunerReading>) {
+    let (rect, response) = ui.allocate_exact_size(Vec2::new(320.0, 40.0), Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 4.0, ui.visuals().extreme_bg_color);
+
+    let dt = ui.input(|input| input.stable_dt).min(0.1);
+    let offset_id = response.id.with("offset");
+    let mut offset: f32 = ui.data_mut(|data| *data.get_temp_mut_or(offset_id, 0.0));
+    if let Some(reading) = reading {
+        offset = (offset + reading.cents as f32 * STROBE_SPEED * dt).rem_euclid(1.0);
+        ui.data_mut(|data| data.insert_temp(offset_id, offset));
+        ui.ctx().request_repaint();
+    }
+
+    let color = match reading {
+        Some(reading) if reading.cents.abs() <= IN_TUNE_CENTS => IN_TUNE_COLOR,
+        Some(_) => OUT_OF_TUNE_COLOR,
+        None => ui.visuals().weak_text_color(),
+    };
+    let stripe_width = rect.width() / STROBE_STRIPES as f32;
+    for stripe in -1..=STROBE_STRIPES as i32 {
+        let left = rect.left() + (stripe as f32 + offset) * stripe_width;
+        let stripe_rect = Rect::from_min_max(
+            Pos2::new(left, rect.top()),
+            Pos2::new(left + stripe_width / 2.0, rect.bottom()),
+        )
+        .intersect(rect);
+        if stripe_rect.width() > 0.0 {
+            painter.rect_filled(stripe_rect, 0.0, color);
+        }
+    }
+}
+
+// ANALYSIS VIEW ----------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: Plots the tail end of the analysed audio, a few periods of the note being played
+*/
+pub fn waveform_plot(ui: &mut egui::Ui, view: &AnalysisView) {
+    Plot::new("Waveform")
+        .height(120.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .include_y(-1.0)
+        .include_y(1.0)
+        .x_axis_formatter(|ms, _range| format!("{:.0} ms", ms))
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(PlotPoints::from(view.waveform.clone())).name("Waveform"));
+        });
+}
+
+/*
+   Purpose: Plots the magnitude spectrum on a log frequency axis, with a gridline at every natural note and markers for the detected pitch
+   Notes: The fundamental is marked in green and each harmonic found in orange. If the green line isn't on the tallest, leftmost peak,
+          the detector has probably latched onto a harmonic.
+*/
+pub fn spectrum_plot(ui: &mut egui::Ui, view: &AnalysisView, reference_a4: f64) {
+    let log_low = VIEW_LOWEST_FREQUENCY.log10();
+    let log_high = VIEW_HIGHEST_FREQUENCY.log10();
+    Plot::new("Spectrum")
+        .height(200.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .include_x(log_low)
+        .include_x(log_high)
+        .include_y(VIEW_FLOOR_DB)
+        .include_y(0.0)
+        .x_grid_spacer(move |input| note_grid(input, reference_a4))
+        .x_axis_formatter(move |x, _range| note_label(10f64.powf(x), reference_a4))
+        .y_axis_formatter(|db, _range| format!("{:.0} dB", db))
+        .label_formatter(move |_name, point| {
+            let frequency = 10f64.powf(point.x);
+            format!(
+                "{:.1} Hz ({})\n{:.0} dB",
+                frequency,
+                describe_pitch(frequency, reference_a4),
+                point.y
+            )
+        })
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(PlotPoints::from(view.spectrum.clone())).name("Spectrum"));
+            if let Some(fundamental) = view.fundamental {
+                plot_ui.vline(
+                    VLine::new(fundamental.log10())
+                        .color(IN_TUNE_COLOR)
+                        .name("Fundamental"),
+                );
+            }
+            for harmonic in &view.harmonics {
+                plot_ui.vline(
+                    VLine::new(harmonic.log10())
+                        .color(OUT_OF_TUNE_COLOR)
+                        .style(LineStyle::dashed_loose())
+                        .name("Harmonics"),
+                );
+            }
+        });
+}
+
+/*
+   Purpose: Places a gridline on every natural note in view, with the C's drawn heavier since they start each octave
+   Notes: egui decides line thickness from step_size, so the C's get an octave's step and the rest a whole tone's.
+*/
+fn note_grid(input: GridInput, reference_a4: f64) -> Vec<GridMark> {
+    let (low, high) = input.bounds;
+    let lowest = Pitch::nearest(10f64.powf(low), reference_a4).0.midi();
+    let highest = Pitch::nearest(10f64.powf(high), reference_a4).0.midi();
+    (lowest..=highest)
+        .map(Pitch::from_midi)
+        .filter(|pitch| pitch.accidental == Accidental::Natural)
+        .map(|pitch| GridMark {
+            value: pitch.frequency(reference_a4).log10(),
+            step_size: if pitch.name == NoteName::C {
+                2f64.log10()
+            } else {
+                2f64.log10() / 6.0
+            },
+        })
+        .collect()
+}
+
+/*
+   Purpose: Labels the frequency axis with note names where the gridlines sit on a note, and in Hz elsewhere
+*/
+fn note_label(frequency: f64, reference_a4: f64) -> String {
+    let (pitch, cents) = Pitch::nearest(frequency, reference_a4);
+    if cents.abs() < 1.0 {
+        pitch.to_string()
+    } else {
+        format!("{:.0} Hz", frequency)
+    }
+}
+
+// SPECTROGRAM ------------------------------------------------------------------------------------------------------------------
+/*
+   Purpose: How spectrogram levels are turned into colours, from quietest to loudest
+   Notes: Viridis is approximated from a handful of points on https://cran.r-project.org/web/packages/viridis/vignettes/intro-to-viridis.html
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColourMap {
+    Greyscale,
+    Heat,
+    Viridis,
+}
+
+impl ColourMap {
+    pub const ALL: [ColourMap; 3] = [ColourMap::Greyscale, ColourMap::Heat, ColourMap::Viridis];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ColourMap::Greyscale => "Greyscale",
+            ColourMap::Heat => "Heat",
+            ColourMap::Viridis => "Viridis",
+        }
+    }
+
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            ColourMap::Greyscale => &[[0, 0, 0], [255, 255, 255]],
+            ColourMap::Heat => &[[0, 0, 0], [200, 30, 0], [255, 200, 0], [255, 255, 255]],
+            ColourMap::Viridis => &[
+                [68, 1, 84],
+                [59, 82, 139],
+                [33, 145, 140],
+                [94, 201, 98],
+                [253, 231, 37],
+            ],
+        }
+    }
+
+    /*
+       Purpose: The colour for a level between 0 (quietest) and 1 (loudest), blending between the map's stops
+    */
+    pub fn colour(&self, level: f32) -> Color32 {
+        let stops = self.stops();
+        let position = level.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let index = (position as usize).min(stops.len() - 2);
+        let blend = position - index as f32;
+        let mix = |channel: usize| {
+            let from = stops[index][channel] as f32;
+            let to = stops[index + 1][channel] as f32;
+            (from + (to - from) * blend) as u8
+        };
+        Color32::from_rgb(mix(0), mix(1), mix(2))
+    }
+}
+
+/*
+   Purpose: The spectrogram's settings and the texture it's drawn into, kept between frames
+   Notes: floor_db is the level drawn as the quietest colour, everything from there up to 0 dBFS is spread over the colour map.
+*/
+pub struct SpectrogramView {
+    pub settings: SpectrogramSettings,
+    pub colour_map: ColourMap,
+    pub floor_db: f32,
+    texture: Option<egui::TextureHandle>,
+}
+
+impl Default for SpectrogramView {
+    fn default() -> Self {
+        Self {
+            settings: SpectrogramSettings::default(),
+            colour_map: ColourMap::Viridis,
+            floor_db: -100.0,
+            texture: None,
+        }
+    }
+}
+
+impl SpectrogramView {
+    /*
+       Purpose: Draws the controls for the fft size, hop, window and colours
+    */
+    pub fn controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("FFT size")
+                .selected_text(self.settings.fft_size.to_string())
+                .show_ui(ui, |ui| {
+                    for size in SPECTROGRAM_FFT_SIZES {
+                        ui.selectable_value(&mut self.settings.fft_size, size, size.to_string());
+                    }
+                });
+            egui::ComboBox::from_label("Window")
+                .selected_text(self.settings.window.label())
+                .show_ui(ui, |ui| {
+                    for window in WindowFunction::ALL {
+                        ui.selectable_value(&mut self.settings.window, window, window.label());
+                    }
+                });
+            egui::ComboBox::from_label("Colours")
+                .selected_text(self.colour_map.label())
+                .show_ui(ui, |ui| {
+                    for colour_map in ColourMap::ALL {
+                        ui.selectable_value(&mut self.colour_map, colour_map, colour_map.label());
+                    }
+                });
+        });
+        // A hop longer than the frame would skip audio, so the slider stops at the fft size
+        self.settings.hop = self.settings.hop.min(self.settings.fft_size);
+        ui.add(
+            egui::Slider::new(&mut self.settings.hop, 128..=self.settings.fft_size)
+                .logarithmic(true)
+                .text("Hop (samples)"),
+        );
+        ui.add(egui::Slider::new(&mut self.floor_db, -140.0..=-20.0).text("Floor (dB)"));
+    }
+
+    /*
+       Purpose: Draws the spectrogram, newest audio on the right and low notes at the bottom, with each C marked on the left
+       Notes: The texture is refilled from the columns on every frame. It's only a few hundred thousand pixels at most.
+    */
+    pub fn show(&mut self, ui: &mut egui::Ui, spectrogram: &Spectrogram, reference_a4: f64) {
+        let width = spectrogram.max_columns();
+        let columns = spectrogram.columns();
+        let empty = width - columns.len().min(width);
+        let range = -self.floor_db;
+
+        let mut image =
+            egui::ColorImage::new([width, SPECTROGRAM_ROWS], self.colour_map.colour(0.0));
+        for (x, column) in columns.iter().enumerate().take(width) {
+            for (row, db) in column.iter().enumerate() {
+                let y = SPECTROGRAM_ROWS - 1 - row;
+                image.pixels[y * width + empty + x] =
+                    self.colour_map.colour((db - self.floor_db) / range);
+            }
+        }
+
+        let texture = match &mut self.texture {
+            Some(texture) => {
+                texture.set(image, egui::TextureOptions::LINEAR);
+                texture
+            }
+            None => self.texture.insert(ui.ctx().load_texture(
+                "spectrogram",
+                image,
+                egui::TextureOptions::LINEAR,
+            )),
+        };
+
+        let size = Vec2::new(ui.available_width(), 256.0);
+        let response = ui.add(egui::Image::new(texture.id(), size));
+
+        // Note labels down the left edge
+        let painter = ui.painter_at(response.rect);
+        let low = SPECTROGRAM_LOWEST_FREQUENCY.log10();
+        let high = SPECTROGRAM_HIGHEST_FREQUENCY.log10();
+        for octave in 0..=8 {
+            let pitch = Pitch::new(NoteName::C, Accidental::Natural, octave);
+            let position = (pitch.frequency(reference_a4).log10() - low) / (high - low);
+            if !(0.0..=1.0).contains(&position) {
+                continue;
+            }
+            let y = response.rect.bottom() - position as f32 * response.rect.height();
+            painter.text(
+                Pos2::new(response.rect.left() + 4.0, y),
+                egui::Align2::LEFT_CENTER,
+                pitch.to_string(),
+                egui::FontId::proportional(11.0),
+                Color32::WHITE,
+            );
+        }
+        ui.label(format!(
+            "Last {:.1} seconds, {:.0} Hz to {:.0} Hz on a log scale",
+            width as f64 * spectrogram.seconds_per_column(),
+            SPECTROGRAM_LOWEST_FREQUENCY,
+            SPECTROGRAM_HIGHEST_FREQUENCY
+        ));
+    }
+}